@@ -9,7 +9,7 @@ pub struct Model {
     #[sea_orm(primary_key, auto_increment = false)]
     pub id: String,
     pub name: String,
-    pub icon_id: String,
+    pub icon_id: Option<String>,
     pub description: Option<String>,
     pub homepage_url: String,
     pub creator_id: i32,
@@ -19,6 +19,28 @@ pub struct Model {
     pub redirect_uris: String,
     #[sea_orm(column_type = "custom(\"LONGTEXT\")")]
     pub grant_types: String,
+    pub first_party: i8,
+    pub require_pkce: i8,
+    #[sea_orm(column_type = "custom(\"LONGTEXT\")", nullable)]
+    pub allowed_scopes: Option<String>,
+    pub id_token_signed_response_alg: Option<String>,
+    pub userinfo_signed_response_alg: Option<String>,
+    pub access_token_format: String,
+    pub token_endpoint_auth_method: String,
+    #[sea_orm(column_type = "custom(\"LONGTEXT\")", nullable)]
+    pub jwks: Option<String>,
+    pub jwks_uri: Option<String>,
+    #[sea_orm(unique)]
+    pub registration_access_token: Option<String>,
+    #[sea_orm(column_type = "custom(\"LONGTEXT\")", nullable)]
+    pub post_logout_redirect_uris: Option<String>,
+    pub backchannel_logout_uri: Option<String>,
+    pub frontchannel_logout_uri: Option<String>,
+    pub require_pushed_authorization_requests: i8,
+    #[sea_orm(column_type = "custom(\"LONGTEXT\")", nullable)]
+    pub response_types: Option<String>,
+    #[sea_orm(column_type = "custom(\"LONGTEXT\")", nullable)]
+    pub request_uris: Option<String>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -29,6 +51,8 @@ pub enum Relation {
     ApplicationSecret,
     #[sea_orm(has_many = "super::authorization_code::Entity")]
     AuthorizationCode,
+    #[sea_orm(has_many = "super::backchannel_logout_delivery::Entity")]
+    BackchannelLogoutDelivery,
     #[sea_orm(
         belongs_to = "super::image::Entity",
         from = "Column::IconId",
@@ -37,8 +61,12 @@ pub enum Relation {
         on_delete = "Cascade"
     )]
     Image,
+    #[sea_orm(has_many = "super::session_participant::Entity")]
+    SessionParticipant,
     #[sea_orm(has_many = "super::token::Entity")]
     Token,
+    #[sea_orm(has_many = "super::token_exchange_policy::Entity")]
+    TokenExchangePolicy,
     #[sea_orm(
         belongs_to = "super::user::Entity",
         from = "Column::CreatorId",
@@ -67,18 +95,36 @@ impl Related<super::authorization_code::Entity> for Entity {
     }
 }
 
+impl Related<super::backchannel_logout_delivery::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::BackchannelLogoutDelivery.def()
+    }
+}
+
 impl Related<super::image::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::Image.def()
     }
 }
 
+impl Related<super::session_participant::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::SessionParticipant.def()
+    }
+}
+
 impl Related<super::token::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::Token.def()
     }
 }
 
+impl Related<super::token_exchange_policy::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::TokenExchangePolicy.def()
+    }
+}
+
 impl Related<super::user::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::User.def()
@@ -15,6 +15,11 @@ pub struct Model {
     pub redirect_uri: String,
     pub expires_at: DateTime,
     pub created_at: DateTime,
+    pub code_challenge: Option<String>,
+    pub code_challenge_method: Option<String>,
+    pub nonce: Option<String>,
+    pub auth_time: Option<DateTime>,
+    pub sid: Option<String>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -4,6 +4,13 @@ pub use super::application::Entity as Application;
 pub use super::application_access_grant::Entity as ApplicationAccessGrant;
 pub use super::application_secret::Entity as ApplicationSecret;
 pub use super::authorization_code::Entity as AuthorizationCode;
+pub use super::backchannel_logout_delivery::Entity as BackchannelLogoutDelivery;
 pub use super::image::Entity as Image;
+pub use super::initial_access_token::Entity as InitialAccessToken;
+pub use super::login_session::Entity as LoginSession;
+pub use super::session_participant::Entity as SessionParticipant;
+pub use super::signing_key::Entity as SigningKey;
 pub use super::token::Entity as Token;
+pub use super::token_denylist::Entity as TokenDenylist;
+pub use super::token_exchange_policy::Entity as TokenExchangePolicy;
 pub use super::user::Entity as User;
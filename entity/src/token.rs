@@ -9,15 +9,26 @@ pub struct Model {
     #[sea_orm(primary_key)]
     pub id: i32,
     pub application_id: String,
-    pub user_id: i32,
+    pub user_id: Option<i32>,
     #[sea_orm(unique)]
     pub access_token: String,
     #[sea_orm(unique)]
-    pub refresh_token: String,
+    pub refresh_token: Option<String>,
     #[sea_orm(column_type = "custom(\"LONGTEXT\")")]
     pub scopes: String,
     pub expires_at: DateTime,
     pub created_at: DateTime,
+    pub family_id: String,
+    pub refresh_token_used_at: Option<DateTime>,
+    pub refresh_expires_at: Option<DateTime>,
+    pub auth_time: Option<DateTime>,
+    pub access_token_revoked_at: Option<DateTime>,
+    pub access_token_format: String,
+    pub sid: Option<String>,
+    pub audience: Option<String>,
+    #[sea_orm(column_type = "custom(\"LONGTEXT\")", nullable)]
+    pub act: Option<String>,
+    pub dpop_jkt: Option<String>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -37,6 +37,10 @@ pub enum Relation {
         on_delete = "SetNull"
     )]
     Image,
+    #[sea_orm(has_many = "super::initial_access_token::Entity")]
+    InitialAccessToken,
+    #[sea_orm(has_many = "super::login_session::Entity")]
+    LoginSession,
     #[sea_orm(has_many = "super::token::Entity")]
     Token,
 }
@@ -71,6 +75,18 @@ impl Related<super::image::Entity> for Entity {
     }
 }
 
+impl Related<super::initial_access_token::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::InitialAccessToken.def()
+    }
+}
+
+impl Related<super::login_session::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::LoginSession.def()
+    }
+}
+
 impl Related<super::token::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::Token.def()
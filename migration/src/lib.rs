@@ -4,6 +4,28 @@ mod m20220101_000001_create_table;
 mod m20220417_000001_add_image;
 mod m20220807_132032_create_applications;
 mod m20250831_000001_add_oidc_tables;
+mod m20261018_000001_add_application_consent;
+mod m20261018_000002_add_pkce;
+mod m20261018_000003_add_authorization_code_nonce;
+mod m20261018_000004_add_refresh_token_rotation;
+mod m20261018_000005_add_client_credentials;
+mod m20261018_000006_add_token_revocation;
+mod m20261018_000007_add_signing_keys;
+mod m20261018_000008_add_id_token_signing_alg;
+mod m20261018_000009_add_userinfo_signing_alg;
+mod m20261018_000010_add_jwt_access_tokens;
+mod m20261018_000011_add_token_endpoint_auth_method;
+mod m20261018_000012_add_client_registration;
+mod m20261018_000013_add_post_logout_redirect_uris;
+mod m20261018_000014_add_backchannel_logout;
+mod m20261018_000015_add_frontchannel_logout;
+mod m20261018_000016_add_pushed_authorization_requests;
+mod m20261018_000017_add_response_types;
+mod m20261018_000018_add_token_exchange;
+mod m20261018_000019_add_dpop;
+mod m20261018_000020_add_request_uris;
+mod m20261018_000021_allow_refresh_token_grant;
+mod m20261018_000022_restrict_token_exchange_policies;
 
 pub struct Migrator;
 
@@ -15,6 +37,28 @@ impl MigratorTrait for Migrator {
             Box::new(m20220417_000001_add_image::Migration),
             Box::new(m20220807_132032_create_applications::Migration),
             Box::new(m20250831_000001_add_oidc_tables::Migration),
+            Box::new(m20261018_000001_add_application_consent::Migration),
+            Box::new(m20261018_000002_add_pkce::Migration),
+            Box::new(m20261018_000003_add_authorization_code_nonce::Migration),
+            Box::new(m20261018_000004_add_refresh_token_rotation::Migration),
+            Box::new(m20261018_000005_add_client_credentials::Migration),
+            Box::new(m20261018_000006_add_token_revocation::Migration),
+            Box::new(m20261018_000007_add_signing_keys::Migration),
+            Box::new(m20261018_000008_add_id_token_signing_alg::Migration),
+            Box::new(m20261018_000009_add_userinfo_signing_alg::Migration),
+            Box::new(m20261018_000010_add_jwt_access_tokens::Migration),
+            Box::new(m20261018_000011_add_token_endpoint_auth_method::Migration),
+            Box::new(m20261018_000012_add_client_registration::Migration),
+            Box::new(m20261018_000013_add_post_logout_redirect_uris::Migration),
+            Box::new(m20261018_000014_add_backchannel_logout::Migration),
+            Box::new(m20261018_000015_add_frontchannel_logout::Migration),
+            Box::new(m20261018_000016_add_pushed_authorization_requests::Migration),
+            Box::new(m20261018_000017_add_response_types::Migration),
+            Box::new(m20261018_000018_add_token_exchange::Migration),
+            Box::new(m20261018_000019_add_dpop::Migration),
+            Box::new(m20261018_000020_add_request_uris::Migration),
+            Box::new(m20261018_000021_allow_refresh_token_grant::Migration),
+            Box::new(m20261018_000022_restrict_token_exchange_policies::Migration),
         ]
     }
 }
@@ -0,0 +1,75 @@
+use sea_orm_migration::prelude::*;
+
+use crate::m20220807_132032_create_applications::Application;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20261018_000001_add_application_consent"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // First-party applications skip the consent screen
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Application::Table)
+                    .add_column(
+                        ColumnDef::new(ApplicationConsent::FirstParty)
+                            .boolean()
+                            .not_null()
+                            .default(Value::Bool(Some(false))),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // A user holds at most one grant per application
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-app-grant-app-user")
+                    .table(ApplicationAccessGrant::Table)
+                    .col(ApplicationAccessGrant::AppId)
+                    .col(ApplicationAccessGrant::UserId)
+                    .unique()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx-app-grant-app-user")
+                    .table(ApplicationAccessGrant::Table)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Application::Table)
+                    .drop_column(ApplicationConsent::FirstParty)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(Iden)]
+enum ApplicationConsent {
+    FirstParty,
+}
+
+#[derive(Iden)]
+enum ApplicationAccessGrant {
+    Table,
+    AppId,
+    UserId,
+}
@@ -0,0 +1,73 @@
+use sea_orm_migration::prelude::*;
+
+use crate::m20220807_132032_create_applications::Application;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20261018_000002_add_pkce"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(AuthorizationCode::Table)
+                    .add_column(ColumnDef::new(AuthorizationCode::CodeChallenge).string())
+                    .add_column(ColumnDef::new(AuthorizationCode::CodeChallengeMethod).string())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Application::Table)
+                    .add_column(
+                        ColumnDef::new(ApplicationPkce::RequirePkce)
+                            .boolean()
+                            .not_null()
+                            .default(Value::Bool(Some(false))),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(AuthorizationCode::Table)
+                    .drop_column(AuthorizationCode::CodeChallenge)
+                    .drop_column(AuthorizationCode::CodeChallengeMethod)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Application::Table)
+                    .drop_column(ApplicationPkce::RequirePkce)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(Iden)]
+enum AuthorizationCode {
+    Table,
+    CodeChallenge,
+    CodeChallengeMethod,
+}
+
+#[derive(Iden)]
+enum ApplicationPkce {
+    RequirePkce,
+}
@@ -0,0 +1,43 @@
+use sea_orm_migration::prelude::*;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20261018_000003_add_authorization_code_nonce"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(AuthorizationCode::Table)
+                    .add_column(ColumnDef::new(AuthorizationCode::Nonce).string())
+                    .add_column(ColumnDef::new(AuthorizationCode::AuthTime).date_time())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(AuthorizationCode::Table)
+                    .drop_column(AuthorizationCode::Nonce)
+                    .drop_column(AuthorizationCode::AuthTime)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(Iden)]
+enum AuthorizationCode {
+    Table,
+    Nonce,
+    AuthTime,
+}
@@ -0,0 +1,83 @@
+use sea_orm::{ConnectionTrait, Statement};
+use sea_orm_migration::prelude::*;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20261018_000004_add_refresh_token_rotation"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Token::Table)
+                    .modify_column(ColumnDef::new(Token::RefreshToken).string().null())
+                    .add_column(
+                        ColumnDef::new(Token::FamilyId)
+                            .string()
+                            .not_null()
+                            .default(Value::String(Some(Box::new(String::from(""))))),
+                    )
+                    .add_column(ColumnDef::new(Token::RefreshTokenUsedAt).date_time())
+                    .add_column(ColumnDef::new(Token::RefreshExpiresAt).date_time())
+                    .add_column(ColumnDef::new(Token::AuthTime).date_time())
+                    .to_owned(),
+            )
+            .await?;
+
+        // Every token issued so far starts its own family
+        let sql = "UPDATE `token` SET `family_id` = UUID() WHERE `family_id` = ''";
+        println!("{}", sql);
+
+        let stmt = Statement::from_string(manager.get_database_backend(), sql.to_owned());
+        manager.get_connection().execute(stmt).await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-token-family-id")
+                    .table(Token::Table)
+                    .col(Token::FamilyId)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx-token-family-id")
+                    .table(Token::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Token::Table)
+                    .drop_column(Token::FamilyId)
+                    .drop_column(Token::RefreshTokenUsedAt)
+                    .drop_column(Token::RefreshExpiresAt)
+                    .drop_column(Token::AuthTime)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(Iden)]
+enum Token {
+    Table,
+    RefreshToken,
+    FamilyId,
+    RefreshTokenUsedAt,
+    RefreshExpiresAt,
+    AuthTime,
+}
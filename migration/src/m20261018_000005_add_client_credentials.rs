@@ -0,0 +1,66 @@
+use sea_orm_migration::prelude::*;
+
+use crate::m20220807_132032_create_applications::Application;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20261018_000005_add_client_credentials"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Machine tokens issued through client_credentials have no subject user
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Token::Table)
+                    .modify_column(ColumnDef::new(Token::UserId).integer().null())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Application::Table)
+                    .add_column(ColumnDef::new(ApplicationClientCredentials::AllowedScopes).json())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Application::Table)
+                    .drop_column(ApplicationClientCredentials::AllowedScopes)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Token::Table)
+                    .modify_column(ColumnDef::new(Token::UserId).integer().not_null())
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(Iden)]
+enum Token {
+    Table,
+    UserId,
+}
+
+#[derive(Iden)]
+enum ApplicationClientCredentials {
+    AllowedScopes,
+}
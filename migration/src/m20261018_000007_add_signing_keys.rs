@@ -0,0 +1,55 @@
+use sea_orm_migration::prelude::*;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20261018_000007_add_signing_keys"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(SigningKey::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(SigningKey::Kid)
+                            .string()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(SigningKey::Alg).string().not_null())
+                    .col(ColumnDef::new(SigningKey::PrivateKey).text().not_null())
+                    .col(ColumnDef::new(SigningKey::Status).string().not_null())
+                    .col(ColumnDef::new(SigningKey::CreatedAt).date_time().not_null())
+                    .col(ColumnDef::new(SigningKey::ActivatedAt).date_time())
+                    .col(ColumnDef::new(SigningKey::RetiredAt).date_time())
+                    .col(ColumnDef::new(SigningKey::ExpiresAt).date_time())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(SigningKey::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+enum SigningKey {
+    Table,
+    Kid,
+    Alg,
+    PrivateKey,
+    Status,
+    CreatedAt,
+    ActivatedAt,
+    RetiredAt,
+    ExpiresAt,
+}
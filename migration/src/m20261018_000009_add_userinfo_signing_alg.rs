@@ -0,0 +1,43 @@
+use sea_orm_migration::prelude::*;
+
+use crate::m20220807_132032_create_applications::Application;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20261018_000009_add_userinfo_signing_alg"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Application::Table)
+                    .add_column(
+                        ColumnDef::new(ApplicationSigningAlg::UserinfoSignedResponseAlg).string(),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Application::Table)
+                    .drop_column(ApplicationSigningAlg::UserinfoSignedResponseAlg)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(Iden)]
+enum ApplicationSigningAlg {
+    UserinfoSignedResponseAlg,
+}
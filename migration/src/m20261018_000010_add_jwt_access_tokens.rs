@@ -0,0 +1,113 @@
+use sea_orm_migration::prelude::*;
+
+use crate::m20220807_132032_create_applications::Application;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20261018_000010_add_jwt_access_tokens"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Application::Table)
+                    .add_column(
+                        ColumnDef::new(ApplicationAccessTokenFormat::AccessTokenFormat)
+                            .string()
+                            .not_null()
+                            .default(Value::String(Some(Box::new(String::from("opaque"))))),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // JWT access tokens are stored by their `jti`
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Token::Table)
+                    .add_column(
+                        ColumnDef::new(Token::AccessTokenFormat)
+                            .string()
+                            .not_null()
+                            .default(Value::String(Some(Box::new(String::from("opaque"))))),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(TokenDenylist::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(TokenDenylist::Jti)
+                            .string()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(TokenDenylist::ExpiresAt)
+                            .date_time()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(TokenDenylist::CreatedAt)
+                            .date_time()
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(TokenDenylist::Table).to_owned())
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Token::Table)
+                    .drop_column(Token::AccessTokenFormat)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Application::Table)
+                    .drop_column(ApplicationAccessTokenFormat::AccessTokenFormat)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(Iden)]
+enum ApplicationAccessTokenFormat {
+    AccessTokenFormat,
+}
+
+#[derive(Iden)]
+enum Token {
+    Table,
+    AccessTokenFormat,
+}
+
+#[derive(Iden)]
+enum TokenDenylist {
+    Table,
+    Jti,
+    ExpiresAt,
+    CreatedAt,
+}
@@ -0,0 +1,55 @@
+use sea_orm_migration::prelude::*;
+
+use crate::m20220807_132032_create_applications::Application;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20261018_000011_add_token_endpoint_auth_method"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Existing applications keep authenticating with the secret in the form body
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Application::Table)
+                    .add_column(
+                        ColumnDef::new(ApplicationClientAuth::TokenEndpointAuthMethod)
+                            .string()
+                            .not_null()
+                            .default(Value::String(Some(Box::new(String::from(
+                                "client_secret_post",
+                            ))))),
+                    )
+                    .add_column(ColumnDef::new(ApplicationClientAuth::Jwks).json())
+                    .add_column(ColumnDef::new(ApplicationClientAuth::JwksUri).string())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Application::Table)
+                    .drop_column(ApplicationClientAuth::TokenEndpointAuthMethod)
+                    .drop_column(ApplicationClientAuth::Jwks)
+                    .drop_column(ApplicationClientAuth::JwksUri)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(Iden)]
+enum ApplicationClientAuth {
+    TokenEndpointAuthMethod,
+    Jwks,
+    JwksUri,
+}
@@ -0,0 +1,128 @@
+use sea_orm_migration::prelude::*;
+
+use crate::{
+    m20220101_000001_create_table::User, m20220807_132032_create_applications::Application,
+};
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20261018_000012_add_client_registration"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Dynamically registered clients have no uploaded icon
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Application::Table)
+                    .modify_column(ColumnDef::new(Application::IconId).string().null())
+                    .add_column(
+                        ColumnDef::new(ApplicationRegistration::RegistrationAccessToken).string(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-app-registration-access-token")
+                    .table(Application::Table)
+                    .col(ApplicationRegistration::RegistrationAccessToken)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(InitialAccessToken::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(InitialAccessToken::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(InitialAccessToken::Token)
+                            .string()
+                            .not_null()
+                            .unique_key(),
+                    )
+                    .col(
+                        ColumnDef::new(InitialAccessToken::UserId)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(InitialAccessToken::ExpiresAt)
+                            .date_time()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(InitialAccessToken::CreatedAt)
+                            .date_time()
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        sea_query::ForeignKey::create()
+                            .name("fk-initial-access-token-to-user-id")
+                            .from_tbl(InitialAccessToken::Table)
+                            .from_col(InitialAccessToken::UserId)
+                            .to(User::Table, User::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(InitialAccessToken::Table).to_owned())
+            .await?;
+
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx-app-registration-access-token")
+                    .table(Application::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Application::Table)
+                    .drop_column(ApplicationRegistration::RegistrationAccessToken)
+                    .modify_column(ColumnDef::new(Application::IconId).string().not_null())
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(Iden)]
+enum ApplicationRegistration {
+    RegistrationAccessToken,
+}
+
+#[derive(Iden)]
+enum InitialAccessToken {
+    Table,
+    Id,
+    Token,
+    UserId,
+    ExpiresAt,
+    CreatedAt,
+}
@@ -0,0 +1,289 @@
+use sea_orm_migration::prelude::*;
+
+use crate::{
+    m20220101_000001_create_table::User, m20220807_132032_create_applications::Application,
+};
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20261018_000014_add_backchannel_logout"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Application::Table)
+                    .add_column(
+                        ColumnDef::new(ApplicationBackchannelLogout::BackchannelLogoutUri).string(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // The login session an authorization code or token was issued in
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(AuthorizationCode::Table)
+                    .add_column(ColumnDef::new(AuthorizationCode::Sid).string())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Token::Table)
+                    .add_column(ColumnDef::new(Token::Sid).string())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(LoginSession::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(LoginSession::Sid)
+                            .string()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(LoginSession::UserId).integer().not_null())
+                    .col(ColumnDef::new(LoginSession::SessionId).string().not_null())
+                    .col(
+                        ColumnDef::new(LoginSession::CreatedAt)
+                            .date_time()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(LoginSession::EndedAt).date_time())
+                    .foreign_key(
+                        sea_query::ForeignKey::create()
+                            .name("fk-login-session-to-user-id")
+                            .from_tbl(LoginSession::Table)
+                            .from_col(LoginSession::UserId)
+                            .to(User::Table, User::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(SessionParticipant::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(SessionParticipant::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(SessionParticipant::Sid).string().not_null())
+                    .col(
+                        ColumnDef::new(SessionParticipant::ApplicationId)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(SessionParticipant::CreatedAt)
+                            .date_time()
+                            .not_null(),
+                    )
+                    .index(
+                        Index::create()
+                            .name("idx-session-participant-sid-app")
+                            .col(SessionParticipant::Sid)
+                            .col(SessionParticipant::ApplicationId)
+                            .unique(),
+                    )
+                    .foreign_key(
+                        sea_query::ForeignKey::create()
+                            .name("fk-session-participant-to-login-session-sid")
+                            .from_tbl(SessionParticipant::Table)
+                            .from_col(SessionParticipant::Sid)
+                            .to(LoginSession::Table, LoginSession::Sid)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        sea_query::ForeignKey::create()
+                            .name("fk-session-participant-to-app-id")
+                            .from_tbl(SessionParticipant::Table)
+                            .from_col(SessionParticipant::ApplicationId)
+                            .to(Application::Table, Application::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(BackchannelLogoutDelivery::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(BackchannelLogoutDelivery::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(BackchannelLogoutDelivery::Sid)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(BackchannelLogoutDelivery::ApplicationId)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(BackchannelLogoutDelivery::LogoutUri)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(BackchannelLogoutDelivery::Status)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(BackchannelLogoutDelivery::Attempts)
+                            .integer()
+                            .not_null()
+                            .default(0),
+                    )
+                    .col(ColumnDef::new(BackchannelLogoutDelivery::LastError).text())
+                    .col(
+                        ColumnDef::new(BackchannelLogoutDelivery::CreatedAt)
+                            .date_time()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(BackchannelLogoutDelivery::UpdatedAt)
+                            .date_time()
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        sea_query::ForeignKey::create()
+                            .name("fk-backchannel-logout-delivery-to-app-id")
+                            .from_tbl(BackchannelLogoutDelivery::Table)
+                            .from_col(BackchannelLogoutDelivery::ApplicationId)
+                            .to(Application::Table, Application::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(
+                Table::drop()
+                    .table(BackchannelLogoutDelivery::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(SessionParticipant::Table).to_owned())
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(LoginSession::Table).to_owned())
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Token::Table)
+                    .drop_column(Token::Sid)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(AuthorizationCode::Table)
+                    .drop_column(AuthorizationCode::Sid)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Application::Table)
+                    .drop_column(ApplicationBackchannelLogout::BackchannelLogoutUri)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(Iden)]
+enum ApplicationBackchannelLogout {
+    BackchannelLogoutUri,
+}
+
+#[derive(Iden)]
+enum AuthorizationCode {
+    Table,
+    Sid,
+}
+
+#[derive(Iden)]
+enum Token {
+    Table,
+    Sid,
+}
+
+#[derive(Iden)]
+enum LoginSession {
+    Table,
+    Sid,
+    UserId,
+    SessionId,
+    CreatedAt,
+    EndedAt,
+}
+
+#[derive(Iden)]
+enum SessionParticipant {
+    Table,
+    Id,
+    Sid,
+    ApplicationId,
+    CreatedAt,
+}
+
+#[derive(Iden)]
+enum BackchannelLogoutDelivery {
+    Table,
+    Id,
+    Sid,
+    ApplicationId,
+    LogoutUri,
+    Status,
+    Attempts,
+    LastError,
+    CreatedAt,
+    UpdatedAt,
+}
@@ -0,0 +1,106 @@
+use sea_orm_migration::prelude::*;
+
+use crate::m20220807_132032_create_applications::Application;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20261018_000018_add_token_exchange"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Exchanged tokens are aimed at another service and record who acts for the subject
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Token::Table)
+                    .add_column(ColumnDef::new(Token::Audience).string())
+                    .add_column(ColumnDef::new(Token::Act).json())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(TokenExchangePolicy::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(TokenExchangePolicy::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(TokenExchangePolicy::ApplicationId)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(TokenExchangePolicy::Audience)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(TokenExchangePolicy::CreatedAt)
+                            .date_time()
+                            .not_null(),
+                    )
+                    .index(
+                        Index::create()
+                            .name("idx-token-exchange-policy-app-audience")
+                            .col(TokenExchangePolicy::ApplicationId)
+                            .col(TokenExchangePolicy::Audience)
+                            .unique(),
+                    )
+                    .foreign_key(
+                        sea_query::ForeignKey::create()
+                            .name("fk-token-exchange-policy-to-app-id")
+                            .from_tbl(TokenExchangePolicy::Table)
+                            .from_col(TokenExchangePolicy::ApplicationId)
+                            .to(Application::Table, Application::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(TokenExchangePolicy::Table).to_owned())
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Token::Table)
+                    .drop_column(Token::Audience)
+                    .drop_column(Token::Act)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(Iden)]
+enum Token {
+    Table,
+    Audience,
+    Act,
+}
+
+#[derive(Iden)]
+enum TokenExchangePolicy {
+    Table,
+    Id,
+    ApplicationId,
+    Audience,
+    CreatedAt,
+}
@@ -0,0 +1,33 @@
+use sea_orm_migration::prelude::*;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20261018_000021_allow_refresh_token_grant"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // The token endpoint now checks every grant against `grant_types`, so
+        // applications that could refresh their tokens so far keep that ability
+        manager
+            .get_connection()
+            .execute_unprepared(
+                r#"UPDATE `application`
+                SET `grant_types` = JSON_ARRAY_APPEND(`grant_types`, '$', 'refresh_token')
+                WHERE JSON_CONTAINS(`grant_types`, '"authorization_code"')
+                AND NOT JSON_CONTAINS(`grant_types`, '"refresh_token"')"#,
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, _manager: &SchemaManager) -> Result<(), DbErr> {
+        // Explicitly listed refresh_token grants cannot be told apart
+        Ok(())
+    }
+}
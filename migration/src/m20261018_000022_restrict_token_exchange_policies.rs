@@ -0,0 +1,34 @@
+use sea_orm_migration::prelude::*;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20261018_000022_restrict_token_exchange_policies"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Audiences now have to be applications whose owner allowed the
+        // exchange. Earlier policies only qualify when one user owns both sides.
+        manager
+            .get_connection()
+            .execute_unprepared(
+                r#"DELETE `policy` FROM `token_exchange_policy` AS `policy`
+                JOIN `application` AS `exchanging` ON `exchanging`.`id` = `policy`.`application_id`
+                LEFT JOIN `application` AS `audience` ON `audience`.`id` = `policy`.`audience`
+                WHERE `audience`.`id` IS NULL
+                OR `audience`.`creator_id` <> `exchanging`.`creator_id`"#,
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, _manager: &SchemaManager) -> Result<(), DbErr> {
+        // Deleted policies cannot be restored
+        Ok(())
+    }
+}
@@ -4,6 +4,20 @@ use tldextract::TldOption;
 use url::Url;
 
 pub const RSA_PRIVATE_KEY_REDIS_KEY: &str = "rsa_private_key";
+pub const CONSENT_REQUEST_REDIS_KEY: &str = "consent_request";
+pub const ACCESS_TOKEN_FORMAT_OPAQUE: &str = "opaque";
+pub const ACCESS_TOKEN_FORMAT_JWT: &str = "jwt";
+pub const CLIENT_ASSERTION_REDIS_KEY: &str = "client_assertion";
+pub const CLIENT_JWKS_REDIS_KEY: &str = "client_jwks";
+pub const DEVICE_CODE_REDIS_KEY: &str = "device_code";
+pub const DEVICE_USER_CODE_REDIS_KEY: &str = "device_user_code";
+pub const PUSHED_AUTHORIZATION_REQUEST_REDIS_KEY: &str = "pushed_authorization_request";
+pub const DPOP_PROOF_REDIS_KEY: &str = "dpop_proof";
+pub const AUTH_METHOD_CLIENT_SECRET_POST: &str = "client_secret_post";
+pub const AUTH_METHOD_CLIENT_SECRET_BASIC: &str = "client_secret_basic";
+pub const AUTH_METHOD_CLIENT_SECRET_JWT: &str = "client_secret_jwt";
+pub const AUTH_METHOD_PRIVATE_KEY_JWT: &str = "private_key_jwt";
+pub const AUTH_METHOD_NONE: &str = "none";
 
 pub struct Envs {
     pub database_url: String,
@@ -17,6 +31,9 @@ pub struct Envs {
     pub bucket_secret_key: String,
     pub bucket_endpoint: String,
     pub cdn_base_url: String,
+    pub signing_key_rotation_days: i64,
+    pub signing_algorithms: Vec<String>,
+    pub access_token_audience: String,
 }
 
 fn env_bool(name: &str) -> bool {
@@ -35,6 +52,8 @@ fn env_or_default(name: &str, default: &str) -> String {
 lazy_static! {
     pub static ref SESSION_COOKIE_KEY: String =
         env_or_default("SESSION_COOKIE_KEY", "delbertbeta-s-sso");
+    pub static ref SECRET_KEY: String =
+        env::var("SECRET_KEY").expect("SECRET_KEY is not set in .env file");
     pub static ref PARSED_FRONTEND_URL: Url = {
         let front_end_url = std::env::var("FRONT_END_URL").expect("FRONT_END_URL not defined");
         Url::parse(&front_end_url).expect("FRONT_END_URL is invalid")
@@ -74,6 +93,18 @@ lazy_static! {
         bucket_endpoint: env::var("BUCKET_ENDPOINT")
             .expect("BUCKET_ENDPOINT is not set in .env file"),
         cdn_base_url: env::var("CDN_BASE_URL").expect("CDN_BASE_URL is not set in .env file"),
+        signing_key_rotation_days: env_or_default("SIGNING_KEY_ROTATION_DAYS", "90")
+            .parse()
+            .expect("SIGNING_KEY_ROTATION_DAYS is invalid"),
+        signing_algorithms: env_or_default("SIGNING_ALGORITHMS", "RS256")
+            .split(',')
+            .map(|alg| alg.trim().to_string())
+            .filter(|alg| !alg.is_empty())
+            .collect(),
+        access_token_audience: env_or_default(
+            "ACCESS_TOKEN_AUDIENCE",
+            PARSED_FRONTEND_URL.as_str()
+        ),
     };
     pub static ref SUPPORT_IMAGE_TYPE: HashSet<&'static str> =
         HashSet::from(["gif", "bmp", "jpg", "jpeg", "png", "webp"]);
@@ -8,3 +8,17 @@ macro_rules! impl_from {
         }
     };
 }
+
+// For error types that wrap `AppError`: accept everything `AppError` can be built from
+#[macro_export]
+macro_rules! impl_from_through {
+    ($through: ident, $for_struct: ident, $($from_struct: ident),+) => {
+        $(
+            impl From<$from_struct> for $for_struct {
+                fn from(inner: $from_struct) -> Self {
+                    $through::from(inner).into()
+                }
+            }
+        )+
+    };
+}
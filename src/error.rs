@@ -1,20 +1,28 @@
 use anyhow::Error as AnyError;
 use axum::{
     extract::rejection::JsonRejection,
-    http::StatusCode,
+    http::{
+        header::{CACHE_CONTROL, WWW_AUTHENTICATE},
+        HeaderValue, StatusCode,
+    },
     response::{IntoResponse, Response},
     Json,
 };
 use openssl::error::ErrorStack;
 use pbkdf2::password_hash::Error as PasswordError;
+use redis::RedisError;
 use sea_orm::DbErr;
+use serde::Serialize;
 use validator::ValidationErrors;
 use volo_grpc::Status;
 
-use crate::response::ErrorResponse;
+use crate::{constants::PARSED_FRONTEND_URL, response::ErrorResponse, util::sign_dpop_nonce};
 
 use jsonwebtoken::errors::Error as JwtError;
 
+// Header names are matched case-insensitively but must be given in lowercase
+const DPOP_NONCE: &str = "dpop-nonce";
+
 #[derive(Debug)]
 pub enum AppError {
     ServiceError(ServiceError),
@@ -25,6 +33,7 @@ pub enum AppError {
     JwtError(JwtError),
     UnexpectedError(AnyError),
     JSONError(JsonRejection),
+    RedisError(RedisError),
 }
 
 impl_from!(ServiceError, AppError, ServiceError);
@@ -35,6 +44,7 @@ impl_from!(JsonRejection, AppError, JSONError);
 impl_from!(AnyError, AppError, UnexpectedError);
 impl_from!(ErrorStack, AppError, RsaError);
 impl_from!(JwtError, AppError, JwtError);
+impl_from!(RedisError, AppError, RedisError);
 
 #[derive(Debug)]
 pub enum ServiceError {
@@ -51,6 +61,20 @@ pub enum ServiceError {
     InvalidGrant,
     InvalidClient,
     InvalidToken,
+    UnsupportedGrantType,
+    InvalidScope,
+    UnauthorizedClient,
+    InvalidRedirectUri,
+    InvalidClientMetadata,
+    AuthorizationPending,
+    SlowDown,
+    ExpiredToken,
+    AccessDenied,
+    InvalidRequest,
+    InvalidRequestObject,
+    InvalidTarget,
+    InvalidDpopProof,
+    UseDpopNonce,
 }
 
 struct ErrorResponseInfo {
@@ -111,6 +135,64 @@ impl AppError {
             AppError::ServiceError(ServiceError::InvalidToken) => {
                 (StatusCode::UNAUTHORIZED, 111, "Invalid token".to_string())
             }
+            AppError::ServiceError(ServiceError::UnsupportedGrantType) => (
+                StatusCode::BAD_REQUEST,
+                113,
+                "Unsupported grant type".to_string(),
+            ),
+            AppError::ServiceError(ServiceError::InvalidScope) => {
+                (StatusCode::BAD_REQUEST, 114, "Invalid scope".to_string())
+            }
+            AppError::ServiceError(ServiceError::UnauthorizedClient) => (
+                StatusCode::BAD_REQUEST,
+                115,
+                "Unauthorized client".to_string(),
+            ),
+            AppError::ServiceError(ServiceError::InvalidRedirectUri) => (
+                StatusCode::BAD_REQUEST,
+                116,
+                "Invalid redirect uri".to_string(),
+            ),
+            AppError::ServiceError(ServiceError::InvalidClientMetadata) => (
+                StatusCode::BAD_REQUEST,
+                117,
+                "Invalid client metadata".to_string(),
+            ),
+            AppError::ServiceError(ServiceError::AuthorizationPending) => (
+                StatusCode::BAD_REQUEST,
+                118,
+                "Authorization pending".to_string(),
+            ),
+            AppError::ServiceError(ServiceError::SlowDown) => {
+                (StatusCode::BAD_REQUEST, 119, "Slow down".to_string())
+            }
+            AppError::ServiceError(ServiceError::ExpiredToken) => {
+                (StatusCode::BAD_REQUEST, 120, "Expired token".to_string())
+            }
+            AppError::ServiceError(ServiceError::AccessDenied) => {
+                (StatusCode::BAD_REQUEST, 121, "Access denied".to_string())
+            }
+            AppError::ServiceError(ServiceError::InvalidRequest) => {
+                (StatusCode::BAD_REQUEST, 122, "Invalid request".to_string())
+            }
+            AppError::ServiceError(ServiceError::InvalidRequestObject) => (
+                StatusCode::BAD_REQUEST,
+                123,
+                "Invalid request object".to_string(),
+            ),
+            AppError::ServiceError(ServiceError::InvalidTarget) => {
+                (StatusCode::BAD_REQUEST, 124, "Invalid target".to_string())
+            }
+            AppError::ServiceError(ServiceError::InvalidDpopProof) => (
+                StatusCode::BAD_REQUEST,
+                125,
+                "Invalid DPoP proof".to_string(),
+            ),
+            AppError::ServiceError(ServiceError::UseDpopNonce) => (
+                StatusCode::BAD_REQUEST,
+                126,
+                "DPoP proof must carry the nonce from the DPoP-Nonce header".to_string(),
+            ),
             AppError::ValidationError(err) => {
                 let message = format!("Input validation error: [{}]", err).replace('\n', ", ");
                 (StatusCode::BAD_REQUEST, 105, message)
@@ -154,6 +236,14 @@ impl AppError {
                     "JWT error".to_string(),
                 )
             }
+            AppError::RedisError(err) => {
+                tracing::error!("Redis error: {:?}", err);
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    505,
+                    "Cache error".to_string(),
+                )
+            }
             AppError::UnexpectedError(err) => {
                 tracing::error!("Unexpected error: {:?}", err);
                 (
@@ -202,3 +292,188 @@ impl IntoResponse for AppError {
         (status, body).into_response()
     }
 }
+
+// Errors of the OAuth and OpenID Connect endpoints, which clients expect in the
+// RFC 6749 format rather than as the `{code, msg}` body of the rest of the API.
+#[derive(Debug)]
+pub struct OAuthError {
+    status: StatusCode,
+    error: &'static str,
+    description: Option<String>,
+    // Raised by a resource endpoint for a DPoP request, which challenges with
+    // the DPoP scheme instead of Bearer
+    dpop: bool,
+}
+
+#[derive(Serialize)]
+struct OAuthErrorBody<'a> {
+    error: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error_description: Option<&'a str>,
+}
+
+impl OAuthError {
+    pub fn new(status: StatusCode, error: &'static str) -> Self {
+        Self {
+            status,
+            error,
+            description: None,
+            dpop: false,
+        }
+    }
+
+    pub fn bad_request(error: &'static str) -> Self {
+        Self::new(StatusCode::BAD_REQUEST, error)
+    }
+
+    pub fn with_description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    // RFC 9449 7.1: resource endpoints reject DPoP requests with 401 and a DPoP challenge
+    pub fn dpop_challenge(mut self) -> Self {
+        if matches!(self.error, "invalid_dpop_proof" | "use_dpop_nonce") {
+            self.status = StatusCode::UNAUTHORIZED;
+        }
+        self.dpop = true;
+        self
+    }
+
+    pub fn error(&self) -> &'static str {
+        self.error
+    }
+
+    pub fn description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+}
+
+impl_from_through!(
+    AppError,
+    OAuthError,
+    ServiceError,
+    DbErr,
+    PasswordError,
+    ValidationErrors,
+    JsonRejection,
+    AnyError,
+    ErrorStack,
+    JwtError,
+    RedisError
+);
+
+impl From<AppError> for OAuthError {
+    fn from(error: AppError) -> Self {
+        let mapped = match &error {
+            AppError::ServiceError(service_error) => match service_error {
+                ServiceError::InvalidGrant => Some((StatusCode::BAD_REQUEST, "invalid_grant")),
+                ServiceError::InvalidClient => Some((StatusCode::UNAUTHORIZED, "invalid_client")),
+                ServiceError::InvalidToken => Some((StatusCode::UNAUTHORIZED, "invalid_token")),
+                ServiceError::UnsupportedGrantType => {
+                    Some((StatusCode::BAD_REQUEST, "unsupported_grant_type"))
+                }
+                ServiceError::InvalidScope => Some((StatusCode::BAD_REQUEST, "invalid_scope")),
+                ServiceError::UnauthorizedClient => {
+                    Some((StatusCode::BAD_REQUEST, "unauthorized_client"))
+                }
+                ServiceError::InvalidRedirectUri => {
+                    Some((StatusCode::BAD_REQUEST, "invalid_redirect_uri"))
+                }
+                ServiceError::InvalidClientMetadata => {
+                    Some((StatusCode::BAD_REQUEST, "invalid_client_metadata"))
+                }
+                ServiceError::AuthorizationPending => {
+                    Some((StatusCode::BAD_REQUEST, "authorization_pending"))
+                }
+                ServiceError::SlowDown => Some((StatusCode::BAD_REQUEST, "slow_down")),
+                ServiceError::ExpiredToken => Some((StatusCode::BAD_REQUEST, "expired_token")),
+                ServiceError::AccessDenied => Some((StatusCode::BAD_REQUEST, "access_denied")),
+                ServiceError::InvalidRequest => Some((StatusCode::BAD_REQUEST, "invalid_request")),
+                ServiceError::InvalidRequestObject => {
+                    Some((StatusCode::BAD_REQUEST, "invalid_request_object"))
+                }
+                ServiceError::InvalidTarget => Some((StatusCode::BAD_REQUEST, "invalid_target")),
+                ServiceError::InvalidDpopProof => {
+                    Some((StatusCode::BAD_REQUEST, "invalid_dpop_proof"))
+                }
+                ServiceError::UseDpopNonce => Some((StatusCode::BAD_REQUEST, "use_dpop_nonce")),
+                ServiceError::LoginRequired => Some((StatusCode::UNAUTHORIZED, "login_required")),
+                ServiceError::PermissionDenied => Some((StatusCode::FORBIDDEN, "access_denied")),
+                _ => None,
+            },
+            AppError::ValidationError(_) | AppError::JSONError(_) => {
+                Some((StatusCode::BAD_REQUEST, "invalid_request"))
+            }
+            _ => None,
+        };
+
+        // Logs internal errors and supplies the description
+        let ErrorResponseInfo {
+            status,
+            error_message,
+            ..
+        } = error.get_error_response_info();
+        let (status, error) = match mapped {
+            Some(mapped) => mapped,
+            None if status.is_server_error() => (StatusCode::INTERNAL_SERVER_ERROR, "server_error"),
+            None => (status, "invalid_request"),
+        };
+
+        Self {
+            status,
+            error,
+            description: Some(error_message),
+            dpop: false,
+        }
+    }
+}
+
+impl IntoResponse for OAuthError {
+    fn into_response(self) -> Response {
+        tracing::warn!(
+            "OAuth error: {}, description: {:?}",
+            self.error,
+            self.description
+        );
+
+        let body = Json(OAuthErrorBody {
+            error: self.error,
+            error_description: self.description.as_deref(),
+        });
+        let mut response = (self.status, body).into_response();
+        let headers = response.headers_mut();
+        headers.insert(CACHE_CONTROL, HeaderValue::from_static("no-store"));
+
+        // RFC 6749 5.2 and RFC 6750 3 name the authentication scheme to retry with
+        let challenge = match self.error {
+            "invalid_client" => Some(format!("Basic realm=\"{}\"", *PARSED_FRONTEND_URL)),
+            "invalid_token" | "invalid_dpop_proof" | "use_dpop_nonce" if self.dpop => {
+                Some(format!(
+                    "DPoP realm=\"{}\", error=\"{}\"",
+                    *PARSED_FRONTEND_URL, self.error
+                ))
+            }
+            "invalid_token" => Some(format!(
+                "Bearer realm=\"{}\", error=\"invalid_token\"",
+                *PARSED_FRONTEND_URL
+            )),
+            _ => None,
+        };
+        if let Some(challenge) = challenge.and_then(|challenge| challenge.parse().ok()) {
+            headers.insert(WWW_AUTHENTICATE, challenge);
+        }
+
+        // RFC 9449 8: hand out the nonce the next proof has to carry
+        if self.error == "use_dpop_nonce" {
+            if let Some(nonce) = sign_dpop_nonce()
+                .ok()
+                .and_then(|nonce| HeaderValue::from_str(&nonce).ok())
+            {
+                headers.insert(DPOP_NONCE, nonce);
+            }
+        }
+
+        response
+    }
+}
@@ -15,6 +15,12 @@ use crate::{
 #[derive(Serialize, Deserialize)]
 pub struct UserIdFromSession {
     pub user_id: i32,
+    // Unix timestamp of the password login, missing on sessions created before it was recorded
+    #[serde(default)]
+    pub auth_time: Option<i64>,
+    // Identifies the login session to relying parties, missing on older sessions
+    #[serde(default)]
+    pub sid: Option<String>,
 }
 
 #[async_trait]
@@ -0,0 +1,48 @@
+use std::time::Duration;
+
+use axum::http::{Method, Request};
+use http_body_util::{BodyExt, Full, Limited};
+use hyper::body::Bytes;
+use hyper_tls::HttpsConnector;
+use hyper_util::{
+    client::legacy::{connect::HttpConnector, Client},
+    rt::TokioExecutor,
+};
+
+const FETCH_TIMEOUT: Duration = Duration::from_secs(10);
+// Far more than any JWK set or request object needs
+const FETCH_BODY_LIMIT: usize = 64 * 1024;
+
+lazy_static! {
+    pub static ref CLIENT_INSTANCE: Client<HttpsConnector<HttpConnector>, Full<Bytes>> =
+        Client::builder(TokioExecutor::new()).build(HttpsConnector::new());
+}
+
+// GET a document a client publishes, giving up on slow or oversized responses.
+pub async fn fetch(uri: &str) -> Result<Bytes, String> {
+    let request = Request::builder()
+        .method(Method::GET)
+        .uri(uri)
+        .body(Full::new(Bytes::new()))
+        .map_err(|err| err.to_string())?;
+
+    let fetch = async {
+        let res = CLIENT_INSTANCE
+            .request(request)
+            .await
+            .map_err(|err| err.to_string())?;
+        if !res.status().is_success() {
+            return Err(format!("unexpected status {}", res.status()));
+        }
+
+        Limited::new(res.into_body(), FETCH_BODY_LIMIT)
+            .collect()
+            .await
+            .map(|body| body.to_bytes())
+            .map_err(|err| err.to_string())
+    };
+
+    tokio::time::timeout(FETCH_TIMEOUT, fetch)
+        .await
+        .map_err(|_| "timed out".to_string())?
+}
@@ -3,6 +3,7 @@ mod custom_macro;
 mod constants;
 mod error;
 mod extractor;
+mod http_client;
 mod model;
 mod response;
 mod route;
@@ -13,7 +14,8 @@ mod util;
 #[macro_use]
 extern crate lazy_static;
 use crate::constants::ENVS;
-use crate::storage::{mysql, session};
+use crate::route::{revoke_user_sessions, OidcKeys};
+use crate::storage::{mysql, redis, session};
 use storage::s3;
 use tracing;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
@@ -32,10 +34,46 @@ async fn main() {
         .init();
 
     let conn = mysql::get_mysql_db_conn().await;
+
+    // `sso-rs rotate-signing-key` promotes the next ID token signing key and exits
+    if std::env::args().nth(1).as_deref() == Some("rotate-signing-key") {
+        OidcKeys::load(&conn)
+            .await
+            .expect("Failed to load OIDC signing keys")
+            .rotate(&conn)
+            .await
+            .expect("Failed to rotate OIDC signing key");
+        return;
+    }
+
+    // `sso-rs revoke-sessions <user_id>` signs a user out everywhere and tells
+    // the relying parties through back-channel logout
+    if std::env::args().nth(1).as_deref() == Some("revoke-sessions") {
+        let user_id: i32 = std::env::args()
+            .nth(2)
+            .and_then(|user_id| user_id.parse().ok())
+            .expect("Usage: sso-rs revoke-sessions <user_id>");
+        let oidc_keys = OidcKeys::load(&conn)
+            .await
+            .expect("Failed to load OIDC signing keys");
+        let revoked = revoke_user_sessions(&conn, &redis::get_redis_client(), &oidc_keys, user_id)
+            .await
+            .expect("Failed to revoke sessions");
+        println!("Revoked {} session(s)", revoked);
+        return;
+    }
+
     let s3_client = s3::get_s3_client().await;
     let session_store = session::get_session_store();
+    let redis_client = redis::get_redis_client();
 
-    let app = route::get_app(conn.clone(), session_store.clone(), s3_client.clone()).await;
+    let app = route::get_app(
+        conn.clone(),
+        session_store.clone(),
+        redis_client,
+        s3_client.clone(),
+    )
+    .await;
     let addr = "0.0.0.0:3000";
     let listener = tokio::net::TcpListener::bind(addr).await.unwrap();
 
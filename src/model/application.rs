@@ -1,8 +1,15 @@
 use chrono::Utc;
 use entity::application::{self, ActiveModel, Entity, Model};
 use entity::image::{Entity as ImageEntity, Model as ImageModel};
+use entity::token;
 use sea_orm::DbErr;
-use sea_orm::{ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, Set};
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, Condition, DatabaseConnection, EntityTrait, QueryFilter, Set,
+    TransactionTrait,
+};
+
+use crate::constants::{ACCESS_TOKEN_FORMAT_OPAQUE, AUTH_METHOD_CLIENT_SECRET_POST};
+use crate::model::token_denylist::delete_tokens;
 
 pub struct ApplicationModel<'a>(&'a DatabaseConnection);
 
@@ -13,26 +20,65 @@ pub struct CreateApplicationParams {
     pub description: Option<String>,
     pub homepage_url: String,
     pub redirect_uris: String,
-    pub grant_types: String,
+    pub grant_types: Vec<String>,
     pub creator_id: i32,
 }
 
-#[derive(Debug)]
-#[allow(dead_code)]
+#[derive(Debug, Default)]
 pub struct UpdateApplicationParams {
-    pub name: String,
-    pub icon_id: String,
+    pub name: Option<String>,
+    pub icon_id: Option<String>,
     pub description: Option<String>,
+    pub homepage_url: Option<String>,
+    pub redirect_uris: Option<String>,
+    pub require_pkce: Option<bool>,
+    pub grant_types: Option<Vec<String>>,
+    pub allowed_scopes: Option<Vec<String>>,
+    pub id_token_signed_response_alg: Option<String>,
+    pub userinfo_signed_response_alg: Option<String>,
+    pub access_token_format: Option<String>,
+    pub token_endpoint_auth_method: Option<String>,
+    pub jwks: Option<String>,
+    pub jwks_uri: Option<String>,
+    pub post_logout_redirect_uris: Option<Vec<String>>,
+    pub backchannel_logout_uri: Option<String>,
+    pub frontchannel_logout_uri: Option<String>,
+    pub require_pushed_authorization_requests: Option<bool>,
+    pub response_types: Option<Vec<String>>,
+    pub request_uris: Option<Vec<String>>,
+}
+
+// Metadata a client manages itself through dynamic registration
+pub struct ClientMetadataParams {
+    pub name: String,
     pub homepage_url: String,
-    pub redirect_uris: String,
+    pub redirect_uris: Vec<String>,
+    pub grant_types: Vec<String>,
+    pub allowed_scopes: Option<Vec<String>>,
+    pub token_endpoint_auth_method: String,
+    pub jwks: Option<String>,
+    pub jwks_uri: Option<String>,
+    pub id_token_signed_response_alg: Option<String>,
+    pub userinfo_signed_response_alg: Option<String>,
+    pub post_logout_redirect_uris: Option<Vec<String>>,
+    pub backchannel_logout_uri: Option<String>,
+    pub frontchannel_logout_uri: Option<String>,
+    pub require_pushed_authorization_requests: bool,
+    pub response_types: Vec<String>,
+    pub request_uris: Option<Vec<String>>,
+}
+
+pub struct RegisterApplicationParams {
+    pub id: String,
+    pub creator_id: i32,
+    pub registration_access_token: String,
+    pub metadata: ClientMetadataParams,
 }
 
 type QueryOptionReturnType = Result<Option<(Model, Option<ImageModel>)>, DbErr>;
 type QueryVecReturnType = Result<Vec<(Model, Option<ImageModel>)>, DbErr>;
-#[allow(dead_code)]
 type QueryOptionNoRelatedReturnType = Result<Option<Model>, DbErr>;
 type QueryReturnType = Result<Model, DbErr>;
-#[allow(dead_code)]
 type UpdateReturnType = Result<ActiveModel, DbErr>;
 
 impl<'a> ApplicationModel<'a> {
@@ -48,6 +94,16 @@ impl<'a> ApplicationModel<'a> {
             .await
     }
 
+    pub async fn find_one_application_by_registration_access_token(
+        &self,
+        registration_access_token: &str,
+    ) -> QueryOptionNoRelatedReturnType {
+        Entity::find()
+            .filter(application::Column::RegistrationAccessToken.eq(registration_access_token))
+            .one(self.0)
+            .await
+    }
+
     pub async fn find_applications_by_user_id(&self, user_id: &i32) -> QueryVecReturnType {
         Entity::find()
             .find_also_related(ImageEntity)
@@ -60,12 +116,28 @@ impl<'a> ApplicationModel<'a> {
         let new_application = ActiveModel {
             id: Set(params.id),
             name: Set(params.name),
-            icon_id: Set(params.icon_id),
+            icon_id: Set(Some(params.icon_id)),
             description: Set(params.description),
             homepage_url: Set(params.homepage_url),
             redirect_uris: Set(serde_json::to_string(&vec![params.redirect_uris]).unwrap()),
-            grant_types: Set(serde_json::to_string(&vec![params.grant_types]).unwrap()),
+            grant_types: Set(serde_json::to_string(&params.grant_types).unwrap()),
             creator_id: Set(params.creator_id),
+            first_party: Set(false as i8),
+            require_pkce: Set(false as i8),
+            allowed_scopes: Set(None),
+            id_token_signed_response_alg: Set(None),
+            userinfo_signed_response_alg: Set(None),
+            access_token_format: Set(ACCESS_TOKEN_FORMAT_OPAQUE.to_string()),
+            token_endpoint_auth_method: Set(AUTH_METHOD_CLIENT_SECRET_POST.to_string()),
+            jwks: Set(None),
+            jwks_uri: Set(None),
+            registration_access_token: Set(None),
+            post_logout_redirect_uris: Set(None),
+            backchannel_logout_uri: Set(None),
+            frontchannel_logout_uri: Set(None),
+            require_pushed_authorization_requests: Set(false as i8),
+            response_types: Set(None),
+            request_uris: Set(None),
             created_at: Set(Utc::now().naive_utc()),
             updated_at: Set(Utc::now().naive_utc()),
         };
@@ -73,24 +145,178 @@ impl<'a> ApplicationModel<'a> {
         new_application.insert(self.0).await
     }
 
-    #[allow(dead_code)]
     pub async fn update_application(
         &self,
         mut active_model: ActiveModel,
         params: UpdateApplicationParams,
     ) -> UpdateReturnType {
-        active_model.name = Set(params.name);
-        active_model.icon_id = Set(params.icon_id);
-        active_model.redirect_uris =
-            Set(serde_json::to_string(&vec![params.redirect_uris]).unwrap());
-        active_model.homepage_url = Set(params.homepage_url);
+        if let Some(name) = params.name {
+            active_model.name = Set(name);
+        }
+
+        if let Some(icon_id) = params.icon_id {
+            active_model.icon_id = Set(Some(icon_id));
+        }
+
+        if let Some(redirect_uris) = params.redirect_uris {
+            active_model.redirect_uris = Set(serde_json::to_string(&vec![redirect_uris]).unwrap());
+        }
+
+        if let Some(homepage_url) = params.homepage_url {
+            active_model.homepage_url = Set(homepage_url);
+        }
 
         if let Some(description) = params.description {
             active_model.description = Set(Some(description));
         }
 
+        if let Some(require_pkce) = params.require_pkce {
+            active_model.require_pkce = Set(require_pkce as i8);
+        }
+
+        if let Some(grant_types) = params.grant_types {
+            active_model.grant_types = Set(serde_json::to_string(&grant_types).unwrap());
+        }
+
+        if let Some(allowed_scopes) = params.allowed_scopes {
+            active_model.allowed_scopes =
+                Set(Some(serde_json::to_string(&allowed_scopes).unwrap()));
+        }
+
+        if let Some(id_token_signed_response_alg) = params.id_token_signed_response_alg {
+            active_model.id_token_signed_response_alg = Set(Some(id_token_signed_response_alg));
+        }
+
+        if let Some(userinfo_signed_response_alg) = params.userinfo_signed_response_alg {
+            active_model.userinfo_signed_response_alg = Set(Some(userinfo_signed_response_alg));
+        }
+
+        if let Some(access_token_format) = params.access_token_format {
+            active_model.access_token_format = Set(access_token_format);
+        }
+
+        if let Some(token_endpoint_auth_method) = params.token_endpoint_auth_method {
+            active_model.token_endpoint_auth_method = Set(token_endpoint_auth_method);
+        }
+
+        if let Some(jwks) = params.jwks {
+            active_model.jwks = Set(Some(jwks));
+        }
+
+        if let Some(jwks_uri) = params.jwks_uri {
+            active_model.jwks_uri = Set(Some(jwks_uri));
+        }
+
+        if let Some(post_logout_redirect_uris) = params.post_logout_redirect_uris {
+            active_model.post_logout_redirect_uris = Set(Some(
+                serde_json::to_string(&post_logout_redirect_uris).unwrap(),
+            ));
+        }
+
+        if let Some(backchannel_logout_uri) = params.backchannel_logout_uri {
+            active_model.backchannel_logout_uri = Set(Some(backchannel_logout_uri));
+        }
+
+        if let Some(frontchannel_logout_uri) = params.frontchannel_logout_uri {
+            active_model.frontchannel_logout_uri = Set(Some(frontchannel_logout_uri));
+        }
+
+        if let Some(require_pushed_authorization_requests) =
+            params.require_pushed_authorization_requests
+        {
+            active_model.require_pushed_authorization_requests =
+                Set(require_pushed_authorization_requests as i8);
+        }
+
+        if let Some(response_types) = params.response_types {
+            active_model.response_types =
+                Set(Some(serde_json::to_string(&response_types).unwrap()));
+        }
+
+        if let Some(request_uris) = params.request_uris {
+            active_model.request_uris = Set(Some(serde_json::to_string(&request_uris).unwrap()));
+        }
+
         active_model.updated_at = Set(Utc::now().naive_utc());
 
         active_model.save(self.0).await
     }
+
+    pub async fn insert_registered_application(
+        &self,
+        params: RegisterApplicationParams,
+    ) -> QueryReturnType {
+        let mut new_application = ActiveModel {
+            id: Set(params.id),
+            icon_id: Set(None),
+            description: Set(None),
+            creator_id: Set(params.creator_id),
+            first_party: Set(false as i8),
+            require_pkce: Set(false as i8),
+            access_token_format: Set(ACCESS_TOKEN_FORMAT_OPAQUE.to_string()),
+            registration_access_token: Set(Some(params.registration_access_token)),
+            created_at: Set(Utc::now().naive_utc()),
+            ..Default::default()
+        };
+        set_client_metadata(&mut new_application, params.metadata);
+
+        new_application.insert(self.0).await
+    }
+
+    // Registration updates replace the client's metadata as a whole (RFC 7592).
+    pub async fn replace_client_metadata(
+        &self,
+        mut active_model: ActiveModel,
+        metadata: ClientMetadataParams,
+    ) -> QueryReturnType {
+        set_client_metadata(&mut active_model, metadata);
+
+        active_model.update(self.0).await
+    }
+
+    pub async fn delete_application(&self, id: &str) -> Result<(), DbErr> {
+        let txn = self.0.begin().await?;
+
+        // Live JWT access tokens are denylisted before the cascade removes them
+        delete_tokens(
+            &txn,
+            Condition::all().add(token::Column::ApplicationId.eq(id)),
+        )
+        .await?;
+        Entity::delete_by_id(id.to_string()).exec(&txn).await?;
+
+        txn.commit().await
+    }
+}
+
+fn set_client_metadata(active_model: &mut ActiveModel, metadata: ClientMetadataParams) {
+    active_model.name = Set(metadata.name);
+    active_model.homepage_url = Set(metadata.homepage_url);
+    active_model.redirect_uris = Set(serde_json::to_string(&metadata.redirect_uris).unwrap());
+    active_model.grant_types = Set(serde_json::to_string(&metadata.grant_types).unwrap());
+    active_model.allowed_scopes = Set(metadata
+        .allowed_scopes
+        .map(|allowed_scopes| serde_json::to_string(&allowed_scopes).unwrap()));
+    active_model.token_endpoint_auth_method = Set(metadata.token_endpoint_auth_method);
+    active_model.jwks = Set(metadata.jwks);
+    active_model.jwks_uri = Set(metadata.jwks_uri);
+    active_model.id_token_signed_response_alg = Set(metadata.id_token_signed_response_alg);
+    active_model.userinfo_signed_response_alg = Set(metadata.userinfo_signed_response_alg);
+    active_model.post_logout_redirect_uris =
+        Set(metadata
+            .post_logout_redirect_uris
+            .map(|post_logout_redirect_uris| {
+                serde_json::to_string(&post_logout_redirect_uris).unwrap()
+            }));
+    active_model.backchannel_logout_uri = Set(metadata.backchannel_logout_uri);
+    active_model.frontchannel_logout_uri = Set(metadata.frontchannel_logout_uri);
+    active_model.require_pushed_authorization_requests =
+        Set(metadata.require_pushed_authorization_requests as i8);
+    active_model.response_types = Set(Some(
+        serde_json::to_string(&metadata.response_types).unwrap(),
+    ));
+    active_model.request_uris = Set(metadata
+        .request_uris
+        .map(|request_uris| serde_json::to_string(&request_uris).unwrap()));
+    active_model.updated_at = Set(Utc::now().naive_utc());
 }
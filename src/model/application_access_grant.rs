@@ -0,0 +1,90 @@
+use chrono::Utc;
+use entity::application::{Entity as ApplicationEntity, Model as ApplicationModel};
+use entity::application_access_grant::{self, ActiveModel, Entity, Model};
+use entity::{authorization_code, token};
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, Condition, DatabaseConnection, DbErr, EntityTrait, ModelTrait,
+    NotSet, QueryFilter, QueryOrder, Set, TransactionTrait,
+};
+
+use super::token_denylist::delete_tokens;
+
+pub struct ApplicationAccessGrantModel<'a>(&'a DatabaseConnection);
+
+type QueryOptionReturnType = Result<Option<Model>, DbErr>;
+type QueryVecReturnType = Result<Vec<(Model, Option<ApplicationModel>)>, DbErr>;
+type QueryReturnType = Result<Model, DbErr>;
+
+impl<'a> ApplicationAccessGrantModel<'a> {
+    pub fn new(conn: &'a DatabaseConnection) -> Self {
+        Self(&conn)
+    }
+
+    pub async fn find_one_grant_by_id(&self, id: &i32) -> QueryOptionReturnType {
+        Entity::find_by_id(id.clone()).one(self.0).await
+    }
+
+    pub async fn find_one_grant(&self, user_id: &i32, app_id: &str) -> QueryOptionReturnType {
+        Entity::find()
+            .filter(application_access_grant::Column::UserId.eq(user_id.clone()))
+            .filter(application_access_grant::Column::AppId.eq(app_id))
+            .one(self.0)
+            .await
+    }
+
+    pub async fn find_grants_by_user_id(&self, user_id: &i32) -> QueryVecReturnType {
+        Entity::find()
+            .find_also_related(ApplicationEntity)
+            .filter(application_access_grant::Column::UserId.eq(user_id.clone()))
+            .order_by_desc(application_access_grant::Column::UpdatedAt)
+            .all(self.0)
+            .await
+    }
+
+    pub async fn save_grant(&self, user_id: &i32, app_id: &str, scopes: String) -> QueryReturnType {
+        let now = Utc::now().naive_utc();
+
+        match self.find_one_grant(user_id, app_id).await? {
+            Some(grant) => {
+                let mut active_model: ActiveModel = grant.into();
+                active_model.scopes = Set(scopes);
+                active_model.updated_at = Set(now);
+                active_model.update(self.0).await
+            }
+            None => {
+                let new_grant = ActiveModel {
+                    id: NotSet,
+                    app_id: Set(app_id.to_string()),
+                    user_id: Set(user_id.clone()),
+                    scopes: Set(scopes),
+                    updated_at: Set(now),
+                    created_at: Set(now),
+                };
+                new_grant.insert(self.0).await
+            }
+        }
+    }
+
+    // Dropping a grant also kills every code and token the app holds for the user
+    pub async fn revoke_grant(&self, grant: Model) -> Result<(), DbErr> {
+        let txn = self.0.begin().await?;
+
+        delete_tokens(
+            &txn,
+            Condition::all()
+                .add(token::Column::UserId.eq(grant.user_id))
+                .add(token::Column::ApplicationId.eq(grant.app_id.clone())),
+        )
+        .await?;
+
+        authorization_code::Entity::delete_many()
+            .filter(authorization_code::Column::UserId.eq(grant.user_id))
+            .filter(authorization_code::Column::ApplicationId.eq(grant.app_id.clone()))
+            .exec(&txn)
+            .await?;
+
+        grant.delete(&txn).await?;
+
+        txn.commit().await
+    }
+}
@@ -0,0 +1,61 @@
+use chrono::Utc;
+use entity::backchannel_logout_delivery::{ActiveModel, Entity, Model};
+use sea_orm::{ActiveModelTrait, ActiveValue::NotSet, DatabaseConnection, DbErr, EntityTrait, Set};
+
+pub const DELIVERY_STATUS_PENDING: &str = "pending";
+pub const DELIVERY_STATUS_DELIVERED: &str = "delivered";
+pub const DELIVERY_STATUS_FAILED: &str = "failed";
+
+pub struct BackchannelLogoutDeliveryModel<'a>(&'a DatabaseConnection);
+
+pub struct CreateDeliveryParams {
+    pub sid: String,
+    pub application_id: String,
+    pub logout_uri: String,
+}
+
+type QueryReturnType = Result<Model, DbErr>;
+
+impl<'a> BackchannelLogoutDeliveryModel<'a> {
+    pub fn new(conn: &'a DatabaseConnection) -> Self {
+        Self(&conn)
+    }
+
+    pub async fn insert_delivery(&self, params: CreateDeliveryParams) -> QueryReturnType {
+        let now = Utc::now().naive_utc();
+        let new_delivery = ActiveModel {
+            id: NotSet,
+            sid: Set(params.sid),
+            application_id: Set(params.application_id),
+            logout_uri: Set(params.logout_uri),
+            status: Set(DELIVERY_STATUS_PENDING.to_string()),
+            attempts: Set(0),
+            last_error: Set(None),
+            created_at: Set(now),
+            updated_at: Set(now),
+        };
+
+        new_delivery.insert(self.0).await
+    }
+
+    pub async fn record_attempt(
+        &self,
+        id: i32,
+        attempts: i32,
+        status: &str,
+        last_error: Option<String>,
+    ) -> QueryReturnType {
+        let delivery = Entity::find_by_id(id)
+            .one(self.0)
+            .await?
+            .ok_or_else(|| DbErr::RecordNotFound("Delivery not found".to_string()))?;
+
+        let mut active_model: ActiveModel = delivery.into();
+        active_model.attempts = Set(attempts);
+        active_model.status = Set(status.to_string());
+        active_model.last_error = Set(last_error);
+        active_model.updated_at = Set(Utc::now().naive_utc());
+
+        active_model.update(self.0).await
+    }
+}
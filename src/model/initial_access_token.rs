@@ -0,0 +1,62 @@
+use chrono::Utc;
+use entity::initial_access_token::{self, ActiveModel, Entity, Model};
+use sea_orm::{
+    ActiveModelTrait, ActiveValue::NotSet, ColumnTrait, DatabaseConnection, DbErr, EntityTrait,
+    QueryFilter, QueryOrder, Set,
+};
+
+type QueryOptionReturnType = Result<Option<Model>, DbErr>;
+type QueryVecReturnType = Result<Vec<Model>, DbErr>;
+type QueryReturnType = Result<Model, DbErr>;
+
+pub struct InitialAccessTokenModel<'a>(&'a DatabaseConnection);
+
+pub struct CreateInitialAccessTokenParams {
+    pub token: String,
+    pub user_id: i32,
+    pub expires_at: chrono::NaiveDateTime,
+}
+
+impl<'a> InitialAccessTokenModel<'a> {
+    pub fn new(conn: &'a DatabaseConnection) -> Self {
+        Self(&conn)
+    }
+
+    pub async fn find_tokens_by_user_id(&self, user_id: &i32) -> QueryVecReturnType {
+        Entity::find()
+            .filter(initial_access_token::Column::UserId.eq(*user_id))
+            .order_by_desc(initial_access_token::Column::CreatedAt)
+            .all(self.0)
+            .await
+    }
+
+    pub async fn find_one_token_by_id(&self, id: &i32) -> QueryOptionReturnType {
+        Entity::find_by_id(*id).one(self.0).await
+    }
+
+    pub async fn find_unexpired_token(&self, token: &str) -> QueryOptionReturnType {
+        Entity::find()
+            .filter(initial_access_token::Column::Token.eq(token))
+            .filter(initial_access_token::Column::ExpiresAt.gt(Utc::now().naive_utc()))
+            .one(self.0)
+            .await
+    }
+
+    pub async fn insert_token(&self, params: CreateInitialAccessTokenParams) -> QueryReturnType {
+        let new_token = ActiveModel {
+            id: NotSet,
+            token: Set(params.token),
+            user_id: Set(params.user_id),
+            expires_at: Set(params.expires_at),
+            created_at: Set(Utc::now().naive_utc()),
+        };
+
+        new_token.insert(self.0).await
+    }
+
+    pub async fn delete_token(&self, id: &i32) -> Result<(), DbErr> {
+        Entity::delete_by_id(*id).exec(self.0).await?;
+
+        Ok(())
+    }
+}
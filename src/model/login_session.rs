@@ -0,0 +1,99 @@
+use chrono::Utc;
+use entity::application;
+use entity::login_session::{self, ActiveModel, Entity, Model};
+use entity::session_participant;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, ConnectionTrait, DatabaseConnection, DbErr, EntityTrait,
+    QueryFilter, QueryOrder, Set,
+};
+
+type QueryOptionReturnType = Result<Option<Model>, DbErr>;
+type QueryVecReturnType = Result<Vec<Model>, DbErr>;
+type QueryReturnType = Result<Model, DbErr>;
+
+pub struct LoginSessionModel<'a>(&'a DatabaseConnection);
+
+pub struct CreateLoginSessionParams {
+    pub sid: String,
+    pub user_id: i32,
+    pub session_id: String,
+}
+
+impl<'a> LoginSessionModel<'a> {
+    pub fn new(conn: &'a DatabaseConnection) -> Self {
+        Self(&conn)
+    }
+
+    pub async fn find_active_sessions_by_user_id(&self, user_id: &i32) -> QueryVecReturnType {
+        Entity::find()
+            .filter(login_session::Column::UserId.eq(*user_id))
+            .filter(login_session::Column::EndedAt.is_null())
+            .order_by_desc(login_session::Column::CreatedAt)
+            .all(self.0)
+            .await
+    }
+
+    pub async fn insert_session(&self, params: CreateLoginSessionParams) -> QueryReturnType {
+        let new_session = ActiveModel {
+            sid: Set(params.sid),
+            user_id: Set(params.user_id),
+            session_id: Set(params.session_id),
+            created_at: Set(Utc::now().naive_utc()),
+            ended_at: Set(None),
+        };
+
+        new_session.insert(self.0).await
+    }
+
+    // Mark the session as ended, returning it only the first time so that
+    // relying parties are notified once.
+    pub async fn end_session(&self, sid: &str) -> QueryOptionReturnType {
+        let session = Entity::find_by_id(sid.to_string())
+            .filter(login_session::Column::EndedAt.is_null())
+            .one(self.0)
+            .await?;
+
+        match session {
+            Some(session) => {
+                let mut active_model: ActiveModel = session.into();
+                active_model.ended_at = Set(Some(Utc::now().naive_utc()));
+                active_model.update(self.0).await.map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    pub async fn find_participants(&self, sid: &str) -> Result<Vec<application::Model>, DbErr> {
+        application::Entity::find()
+            .inner_join(session_participant::Entity)
+            .filter(session_participant::Column::Sid.eq(sid))
+            .all(self.0)
+            .await
+    }
+}
+
+// Remember that the application received tokens in this login session.
+pub async fn record_participant<C: ConnectionTrait>(
+    db: &C,
+    sid: &str,
+    application_id: &str,
+) -> Result<(), DbErr> {
+    let participant = session_participant::Entity::find()
+        .filter(session_participant::Column::Sid.eq(sid))
+        .filter(session_participant::Column::ApplicationId.eq(application_id))
+        .one(db)
+        .await?;
+
+    if participant.is_none() {
+        session_participant::ActiveModel {
+            sid: Set(sid.to_string()),
+            application_id: Set(application_id.to_string()),
+            created_at: Set(Utc::now().naive_utc()),
+            ..Default::default()
+        }
+        .insert(db)
+        .await?;
+    }
+
+    Ok(())
+}
@@ -1,4 +1,11 @@
 pub mod application;
+pub mod application_access_grant;
 pub mod application_secret;
+pub mod backchannel_logout_delivery;
 pub mod image;
+pub mod initial_access_token;
+pub mod login_session;
+pub mod signing_key;
+pub mod token_denylist;
+pub mod token_exchange_policy;
 pub mod user;
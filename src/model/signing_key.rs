@@ -0,0 +1,120 @@
+use chrono::{NaiveDateTime, Utc};
+use entity::signing_key::{self, ActiveModel, Entity, Model};
+use sea_orm::{
+    sea_query::Expr, ActiveModelTrait, ColumnTrait, Condition, DatabaseConnection, DbErr,
+    DeleteResult, EntityTrait, QueryFilter, QueryOrder, Set, TransactionTrait,
+};
+
+pub const KEY_STATUS_PENDING: &str = "pending";
+pub const KEY_STATUS_ACTIVE: &str = "active";
+pub const KEY_STATUS_RETIRED: &str = "retired";
+
+pub struct SigningKeyModel<'a>(&'a DatabaseConnection);
+
+pub struct CreateSigningKeyParams {
+    pub kid: String,
+    pub alg: String,
+    pub private_key: String,
+    pub status: &'static str,
+}
+
+type QueryVecReturnType = Result<Vec<Model>, DbErr>;
+type QueryReturnType = Result<Model, DbErr>;
+
+impl<'a> SigningKeyModel<'a> {
+    pub fn new(conn: &'a DatabaseConnection) -> Self {
+        Self(&conn)
+    }
+
+    // Pending and active keys, plus retired keys that verifiers may still need
+    pub async fn find_published_keys(&self) -> QueryVecReturnType {
+        Entity::find()
+            .filter(
+                Condition::any()
+                    .add(signing_key::Column::Status.is_in([KEY_STATUS_PENDING, KEY_STATUS_ACTIVE]))
+                    .add(signing_key::Column::ExpiresAt.gt(Utc::now().naive_utc())),
+            )
+            .order_by_desc(signing_key::Column::CreatedAt)
+            .all(self.0)
+            .await
+    }
+
+    pub async fn insert_key(&self, params: CreateSigningKeyParams) -> QueryReturnType {
+        let now = Utc::now().naive_utc();
+
+        let new_key = ActiveModel {
+            kid: Set(params.kid),
+            alg: Set(params.alg),
+            private_key: Set(params.private_key),
+            status: Set(params.status.to_string()),
+            created_at: Set(now),
+            activated_at: Set((params.status == KEY_STATUS_ACTIVE).then_some(now)),
+            retired_at: Set(None),
+            expires_at: Set(None),
+        };
+
+        new_key.insert(self.0).await
+    }
+
+    // Promote `kid` to active and retire every other active key of the same
+    // algorithm. Retired keys stay published until `retain_until` so
+    // already-issued tokens keep verifying.
+    pub async fn activate_key(
+        &self,
+        kid: &str,
+        alg: &str,
+        retain_until: NaiveDateTime,
+    ) -> Result<(), DbErr> {
+        let now = Utc::now().naive_utc();
+        let txn = self.0.begin().await?;
+
+        Entity::update_many()
+            .col_expr(signing_key::Column::Status, Expr::value(KEY_STATUS_RETIRED))
+            .col_expr(signing_key::Column::RetiredAt, Expr::value(now))
+            .col_expr(signing_key::Column::ExpiresAt, Expr::value(retain_until))
+            .filter(signing_key::Column::Status.eq(KEY_STATUS_ACTIVE))
+            .filter(signing_key::Column::Alg.eq(alg))
+            .filter(signing_key::Column::Kid.ne(kid))
+            .exec(&txn)
+            .await?;
+
+        Entity::update_many()
+            .col_expr(signing_key::Column::Status, Expr::value(KEY_STATUS_ACTIVE))
+            .col_expr(signing_key::Column::ActivatedAt, Expr::value(now))
+            .filter(signing_key::Column::Kid.eq(kid))
+            .exec(&txn)
+            .await?;
+
+        txn.commit().await
+    }
+
+    // Keys of algorithms that have been switched off stop signing but remain
+    // published for the retention period.
+    pub async fn retire_keys_except(
+        &self,
+        algs: &[String],
+        retain_until: NaiveDateTime,
+    ) -> Result<(), DbErr> {
+        Entity::update_many()
+            .col_expr(signing_key::Column::Status, Expr::value(KEY_STATUS_RETIRED))
+            .col_expr(
+                signing_key::Column::RetiredAt,
+                Expr::value(Utc::now().naive_utc()),
+            )
+            .col_expr(signing_key::Column::ExpiresAt, Expr::value(retain_until))
+            .filter(signing_key::Column::Status.is_in([KEY_STATUS_PENDING, KEY_STATUS_ACTIVE]))
+            .filter(signing_key::Column::Alg.is_not_in(algs.iter().cloned()))
+            .exec(self.0)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn delete_expired_keys(&self) -> Result<DeleteResult, DbErr> {
+        Entity::delete_many()
+            .filter(signing_key::Column::Status.eq(KEY_STATUS_RETIRED))
+            .filter(signing_key::Column::ExpiresAt.lte(Utc::now().naive_utc()))
+            .exec(self.0)
+            .await
+    }
+}
@@ -0,0 +1,77 @@
+use chrono::Utc;
+use entity::token;
+use entity::token_denylist::{self, ActiveModel, Entity, Model};
+use sea_orm::{
+    ColumnTrait, Condition, ConnectionTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter,
+    QueryOrder, Set,
+};
+
+use crate::constants::ACCESS_TOKEN_FORMAT_JWT;
+
+pub struct TokenDenylistModel<'a>(&'a DatabaseConnection);
+
+type QueryVecReturnType = Result<Vec<Model>, DbErr>;
+
+impl<'a> TokenDenylistModel<'a> {
+    pub fn new(conn: &'a DatabaseConnection) -> Self {
+        Self(&conn)
+    }
+
+    pub async fn find_active_entries(&self) -> QueryVecReturnType {
+        Entity::find()
+            .filter(token_denylist::Column::ExpiresAt.gt(Utc::now().naive_utc()))
+            .order_by_desc(token_denylist::Column::CreatedAt)
+            .all(self.0)
+            .await
+    }
+}
+
+// JWT access tokens are validated offline by resource servers, so revoking one
+// has to publish its `jti` until the token would have expired anyway.
+pub async fn deny_access_tokens<C: ConnectionTrait>(
+    db: &C,
+    tokens: &[token::Model],
+) -> Result<(), DbErr> {
+    let now = Utc::now().naive_utc();
+
+    Entity::delete_many()
+        .filter(token_denylist::Column::ExpiresAt.lte(now))
+        .exec(db)
+        .await?;
+
+    let entries: Vec<ActiveModel> = tokens
+        .iter()
+        .filter(|token| {
+            token.access_token_format == ACCESS_TOKEN_FORMAT_JWT
+                && token.access_token_revoked_at.is_none()
+                && token.expires_at > now
+        })
+        .map(|token| ActiveModel {
+            jti: Set(token.access_token.clone()),
+            expires_at: Set(token.expires_at),
+            created_at: Set(now),
+        })
+        .collect();
+
+    if !entries.is_empty() {
+        Entity::insert_many(entries).exec(db).await?;
+    }
+
+    Ok(())
+}
+
+// Delete every token matching `condition`, denylisting the live JWT access tokens first.
+pub async fn delete_tokens<C: ConnectionTrait>(db: &C, condition: Condition) -> Result<(), DbErr> {
+    let tokens = token::Entity::find()
+        .filter(condition.clone())
+        .all(db)
+        .await?;
+    deny_access_tokens(db, &tokens).await?;
+
+    token::Entity::delete_many()
+        .filter(condition)
+        .exec(db)
+        .await?;
+
+    Ok(())
+}
@@ -0,0 +1,63 @@
+use chrono::Utc;
+use entity::token_exchange_policy::{self, ActiveModel, Entity, Model};
+use sea_orm::{
+    ActiveModelTrait, ActiveValue::NotSet, ColumnTrait, DatabaseConnection, DbErr, EntityTrait,
+    QueryFilter, QueryOrder, Set,
+};
+
+type QueryOptionReturnType = Result<Option<Model>, DbErr>;
+type QueryVecReturnType = Result<Vec<Model>, DbErr>;
+type QueryReturnType = Result<Model, DbErr>;
+
+pub struct TokenExchangePolicyModel<'a>(&'a DatabaseConnection);
+
+impl<'a> TokenExchangePolicyModel<'a> {
+    pub fn new(conn: &'a DatabaseConnection) -> Self {
+        Self(&conn)
+    }
+
+    pub async fn find_policies_by_application_id(
+        &self,
+        application_id: &str,
+    ) -> QueryVecReturnType {
+        Entity::find()
+            .filter(token_exchange_policy::Column::ApplicationId.eq(application_id))
+            .order_by_asc(token_exchange_policy::Column::Audience)
+            .all(self.0)
+            .await
+    }
+
+    pub async fn find_one_policy_by_id(&self, id: &i32) -> QueryOptionReturnType {
+        Entity::find_by_id(*id).one(self.0).await
+    }
+
+    // The policy that lets the application exchange tokens for the audience
+    pub async fn find_one_policy(
+        &self,
+        application_id: &str,
+        audience: &str,
+    ) -> QueryOptionReturnType {
+        Entity::find()
+            .filter(token_exchange_policy::Column::ApplicationId.eq(application_id))
+            .filter(token_exchange_policy::Column::Audience.eq(audience))
+            .one(self.0)
+            .await
+    }
+
+    pub async fn insert_policy(&self, application_id: &str, audience: &str) -> QueryReturnType {
+        let new_policy = ActiveModel {
+            id: NotSet,
+            application_id: Set(application_id.to_string()),
+            audience: Set(audience.to_string()),
+            created_at: Set(Utc::now().naive_utc()),
+        };
+
+        new_policy.insert(self.0).await
+    }
+
+    pub async fn delete_policy(&self, id: &i32) -> Result<(), DbErr> {
+        Entity::delete_by_id(*id).exec(self.0).await?;
+
+        Ok(())
+    }
+}
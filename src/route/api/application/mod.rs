@@ -1,4 +1,6 @@
 pub mod get_list;
+pub mod patch;
 pub mod post;
 pub mod secret;
 pub mod single;
+pub mod token_exchange_policy;
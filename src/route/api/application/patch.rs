@@ -0,0 +1,282 @@
+use axum::extract::{Extension, Path};
+use axum::Json;
+use jsonwebtoken::jwk::JwkSet;
+use sea_orm::DatabaseConnection;
+use serde::{Deserialize, Serialize};
+use validator::{Validate, ValidationError};
+
+use crate::{
+    constants::{ACCESS_TOKEN_FORMAT_JWT, ACCESS_TOKEN_FORMAT_OPAQUE},
+    error::{AppError, ServiceError},
+    extractor::user_id_from_session::UserIdFromSession,
+    model::{
+        application::{ApplicationModel, UpdateApplicationParams},
+        image::ImageModel,
+    },
+    response::OkResponse,
+    route::api::oidc::{
+        client_auth::TOKEN_ENDPOINT_AUTH_METHODS,
+        keys::is_enabled_signing_algorithm,
+        register::is_valid_redirect_uri,
+        response::{normalize_response_type, RESPONSE_TYPES},
+        scope::parse_scope,
+        token::GRANT_TYPES,
+    },
+};
+
+#[derive(Serialize)]
+pub struct SuccessResponse {}
+
+#[derive(Deserialize)]
+pub struct PatchApplicationUrlParams {
+    pub application_id: String,
+}
+
+fn validate_grant_types(grant_types: &[String]) -> Result<(), ValidationError> {
+    match grant_types
+        .iter()
+        .all(|grant_type| GRANT_TYPES.contains(&grant_type.as_str()))
+    {
+        true => Ok(()),
+        false => Err(ValidationError::new("Unsupported grant type")),
+    }
+}
+
+fn validate_response_types(response_types: &[String]) -> Result<(), ValidationError> {
+    match response_types.iter().all(|response_type| {
+        RESPONSE_TYPES.contains(&normalize_response_type(response_type).as_str())
+    }) {
+        true => Ok(()),
+        false => Err(ValidationError::new("Unsupported response type")),
+    }
+}
+
+fn validate_signing_alg(alg: &str) -> Result<(), ValidationError> {
+    match is_enabled_signing_algorithm(alg) {
+        true => Ok(()),
+        false => Err(ValidationError::new("Unsupported signing algorithm")),
+    }
+}
+
+fn validate_access_token_format(format: &str) -> Result<(), ValidationError> {
+    match format == ACCESS_TOKEN_FORMAT_OPAQUE || format == ACCESS_TOKEN_FORMAT_JWT {
+        true => Ok(()),
+        false => Err(ValidationError::new("Unsupported access token format")),
+    }
+}
+
+fn validate_token_endpoint_auth_method(method: &str) -> Result<(), ValidationError> {
+    match TOKEN_ENDPOINT_AUTH_METHODS.contains(&method) {
+        true => Ok(()),
+        false => Err(ValidationError::new(
+            "Unsupported token endpoint auth method",
+        )),
+    }
+}
+
+fn validate_jwks(jwks: &serde_json::Value) -> Result<(), ValidationError> {
+    match serde_json::from_value::<JwkSet>(jwks.clone()) {
+        Ok(_) => Ok(()),
+        Err(_) => Err(ValidationError::new("Invalid JWK set")),
+    }
+}
+
+// Keys are fetched by the server, so only over https, as registration requires
+fn validate_jwks_uri(jwks_uri: &str) -> Result<(), ValidationError> {
+    match url::Url::parse(jwks_uri).map_or(false, |url| url.scheme() == "https") {
+        true => Ok(()),
+        false => Err(ValidationError::new("Invalid jwks uri")),
+    }
+}
+
+fn validate_redirect_uris(redirect_uris: &[String]) -> Result<(), ValidationError> {
+    match redirect_uris
+        .iter()
+        .all(|redirect_uri| is_valid_redirect_uri(redirect_uri))
+    {
+        true => Ok(()),
+        false => Err(ValidationError::new("Invalid redirect uri")),
+    }
+}
+
+// Same rules as redirect URIs: absolute http(s) without a fragment
+fn validate_logout_uri(logout_uri: &str) -> Result<(), ValidationError> {
+    match is_valid_redirect_uri(logout_uri) {
+        true => Ok(()),
+        false => Err(ValidationError::new("Invalid logout uri")),
+    }
+}
+
+// Request objects are only fetched over https
+fn validate_request_uris(request_uris: &[String]) -> Result<(), ValidationError> {
+    match request_uris.iter().all(|request_uri| {
+        url::Url::parse(request_uri).map_or(false, |url| url.scheme() == "https")
+    }) {
+        true => Ok(()),
+        false => Err(ValidationError::new("Invalid request uri")),
+    }
+}
+
+#[derive(Deserialize, Validate)]
+pub struct PatchApplicationParams {
+    #[validate(
+        length(min = 1, max = 24),
+        non_control_character,
+        custom(function = "crate::util::validate_padding_string")
+    )]
+    name: Option<String>,
+
+    #[validate(
+        length(min = 1, max = 250),
+        non_control_character,
+        custom(function = "crate::util::validate_padding_string")
+    )]
+    description: Option<String>,
+
+    #[validate(url)]
+    homepage_url: Option<String>,
+
+    #[validate(url)]
+    redirect_uris: Option<String>,
+
+    #[validate(
+        length(equal = 36),
+        non_control_character,
+        custom(function = "crate::util::validate_padding_string")
+    )]
+    icon_id: Option<String>,
+
+    require_pkce: Option<bool>,
+
+    #[validate(custom(function = "validate_grant_types"))]
+    grant_types: Option<Vec<String>>,
+
+    // Space-delimited scopes the application may request with client_credentials
+    #[validate(length(max = 1000), non_control_character)]
+    allowed_scopes: Option<String>,
+
+    #[validate(custom(function = "validate_signing_alg"))]
+    id_token_signed_response_alg: Option<String>,
+
+    // When set, userinfo is returned as a JWT signed with this algorithm
+    #[validate(custom(function = "validate_signing_alg"))]
+    userinfo_signed_response_alg: Option<String>,
+
+    #[validate(custom(function = "validate_access_token_format"))]
+    access_token_format: Option<String>,
+
+    #[validate(custom(function = "validate_token_endpoint_auth_method"))]
+    token_endpoint_auth_method: Option<String>,
+
+    // Public keys used to verify private_key_jwt client assertions
+    #[validate(custom(function = "validate_jwks"))]
+    jwks: Option<serde_json::Value>,
+
+    #[validate(custom(function = "validate_jwks_uri"))]
+    jwks_uri: Option<String>,
+
+    // Where the end_session endpoint may send the browser after logout
+    #[validate(custom(function = "validate_redirect_uris"))]
+    post_logout_redirect_uris: Option<Vec<String>>,
+
+    // Receives a logout token whenever a session the application took part in ends
+    #[validate(custom(function = "validate_logout_uri"))]
+    backchannel_logout_uri: Option<String>,
+
+    // Loaded in an iframe by the logout page, for apps that clear state in the browser
+    #[validate(custom(function = "validate_logout_uri"))]
+    frontchannel_logout_uri: Option<String>,
+
+    // Only accept authorization requests pushed to the PAR endpoint first
+    require_pushed_authorization_requests: Option<bool>,
+
+    // The response types the application may request at the authorize endpoint
+    #[validate(custom(function = "validate_response_types"))]
+    response_types: Option<Vec<String>>,
+
+    // Where the authorize endpoint may fetch the application's request objects from
+    #[validate(custom(function = "validate_request_uris"))]
+    request_uris: Option<Vec<String>>,
+}
+
+pub async fn handler(
+    Extension(conn): Extension<DatabaseConnection>,
+    Path(url_params): Path<PatchApplicationUrlParams>,
+    user_id_from_session: UserIdFromSession,
+    Json(patch_params): Json<PatchApplicationParams>,
+) -> Result<OkResponse<SuccessResponse>, AppError> {
+    patch_params.validate()?;
+
+    let application_model = ApplicationModel::new(&conn);
+
+    let (application, _) = application_model
+        .find_one_application_by_id(url_params.application_id.as_str())
+        .await?
+        .ok_or(ServiceError::NotFound)?;
+
+    if application.creator_id != user_id_from_session.user_id {
+        return Err(ServiceError::PermissionDenied.into());
+    }
+
+    if let Some(icon_id) = &patch_params.icon_id {
+        let image_model = ImageModel::new(&conn);
+        let icon = image_model.find_one_image_by_id(icon_id).await?;
+
+        if icon.map_or(true, |icon| {
+            icon.user_id != user_id_from_session.user_id || icon.uploaded != Some(1)
+        }) {
+            return Err(ServiceError::ImageNotFound.into());
+        }
+    }
+
+    application_model
+        .update_application(
+            application.into(),
+            UpdateApplicationParams {
+                name: patch_params.name,
+                icon_id: patch_params.icon_id,
+                description: patch_params.description,
+                homepage_url: patch_params.homepage_url,
+                redirect_uris: patch_params.redirect_uris,
+                require_pkce: patch_params.require_pkce,
+                grant_types: patch_params.grant_types,
+                allowed_scopes: patch_params
+                    .allowed_scopes
+                    .map(|scope| parse_scope(&scope).into_iter().collect()),
+                id_token_signed_response_alg: patch_params.id_token_signed_response_alg,
+                userinfo_signed_response_alg: patch_params.userinfo_signed_response_alg,
+                access_token_format: patch_params.access_token_format,
+                token_endpoint_auth_method: patch_params.token_endpoint_auth_method,
+                jwks: patch_params.jwks.map(|jwks| jwks.to_string()),
+                jwks_uri: patch_params.jwks_uri,
+                post_logout_redirect_uris: patch_params.post_logout_redirect_uris,
+                backchannel_logout_uri: patch_params.backchannel_logout_uri,
+                frontchannel_logout_uri: patch_params.frontchannel_logout_uri,
+                require_pushed_authorization_requests: patch_params
+                    .require_pushed_authorization_requests,
+                response_types: patch_params.response_types.map(|response_types| {
+                    response_types
+                        .iter()
+                        .map(|response_type| normalize_response_type(response_type))
+                        .collect()
+                }),
+                request_uris: patch_params.request_uris,
+            },
+        )
+        .await?;
+
+    Ok(OkResponse::new(SuccessResponse {}))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{validate_logout_uri, validate_redirect_uris};
+
+    #[test]
+    fn logout_uris_must_be_http() {
+        assert!(validate_redirect_uris(&["https://app.example.com/bye".to_string()]).is_ok());
+        assert!(validate_redirect_uris(&["javascript:alert(1)//".to_string()]).is_err());
+        assert!(validate_logout_uri("https://app.example.com/logout").is_ok());
+        assert!(validate_logout_uri("file:///etc/passwd").is_err());
+    }
+}
@@ -79,7 +79,10 @@ pub async fn handler(
             description,
             homepage_url,
             redirect_uris,
-            grant_types: "authorization_code".to_string(),
+            grant_types: vec![
+                "authorization_code".to_string(),
+                "refresh_token".to_string(),
+            ],
             creator_id: user_id_from_session.user_id,
         })
         .await?;
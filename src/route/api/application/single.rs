@@ -33,6 +33,20 @@ pub struct SuccessResponse {
     homepage_url: String,
     redirect_uris: String,
     grant_types: String,
+    require_pkce: bool,
+    allowed_scopes: Option<String>,
+    id_token_signed_response_alg: Option<String>,
+    userinfo_signed_response_alg: Option<String>,
+    access_token_format: String,
+    token_endpoint_auth_method: String,
+    jwks: Option<String>,
+    jwks_uri: Option<String>,
+    post_logout_redirect_uris: Option<String>,
+    backchannel_logout_uri: Option<String>,
+    frontchannel_logout_uri: Option<String>,
+    require_pushed_authorization_requests: bool,
+    response_types: Option<String>,
+    request_uris: Option<String>,
 }
 
 pub async fn handler(
@@ -74,6 +88,21 @@ pub async fn handler(
         homepage_url: application.homepage_url,
         redirect_uris: application.redirect_uris,
         grant_types: application.grant_types,
+        require_pkce: application.require_pkce != 0,
+        allowed_scopes: application.allowed_scopes,
+        id_token_signed_response_alg: application.id_token_signed_response_alg,
+        userinfo_signed_response_alg: application.userinfo_signed_response_alg,
+        access_token_format: application.access_token_format,
+        token_endpoint_auth_method: application.token_endpoint_auth_method,
+        jwks: application.jwks,
+        jwks_uri: application.jwks_uri,
+        post_logout_redirect_uris: application.post_logout_redirect_uris,
+        backchannel_logout_uri: application.backchannel_logout_uri,
+        frontchannel_logout_uri: application.frontchannel_logout_uri,
+        require_pushed_authorization_requests: application.require_pushed_authorization_requests
+            != 0,
+        response_types: application.response_types,
+        request_uris: application.request_uris,
     };
 
     Ok(OkResponse::new(res))
@@ -0,0 +1,90 @@
+use axum::{
+    extract::{Extension, Path},
+    Json,
+};
+use sea_orm::DatabaseConnection;
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+use crate::{
+    error::{AppError, ServiceError},
+    extractor::user_id_from_session::UserIdFromSession,
+    model::{application::ApplicationModel, token_exchange_policy::TokenExchangePolicyModel},
+    response::OkResponse,
+};
+
+#[derive(Serialize)]
+struct ResponseTokenExchangePolicy {
+    id: i32,
+    audience: String,
+    created_at: String,
+}
+
+#[derive(Deserialize)]
+pub struct CreatePolicyUrlParams {
+    pub application_id: String,
+}
+
+#[derive(Deserialize, Validate)]
+pub struct CreatePolicyPostParams {
+    #[validate(
+        length(min = 1, max = 255),
+        non_control_character,
+        custom(function = "crate::util::validate_padding_string")
+    )]
+    audience: String,
+}
+
+#[derive(Serialize)]
+pub struct SuccessResponse {
+    token_exchange_policy: ResponseTokenExchangePolicy,
+}
+
+// Lets the application exchange the tokens it is given for tokens aimed at
+// `audience`, another application. Only the owner of that application may
+// allow it. Adding an audience twice returns the existing policy.
+pub async fn handler(
+    Extension(conn): Extension<DatabaseConnection>,
+    Path(url_params): Path<CreatePolicyUrlParams>,
+    user_id_from_session: UserIdFromSession,
+    Json(create_params): Json<CreatePolicyPostParams>,
+) -> Result<OkResponse<SuccessResponse>, AppError> {
+    create_params.validate()?;
+
+    let application_model = ApplicationModel::new(&conn);
+    application_model
+        .find_one_application_by_id(url_params.application_id.as_str())
+        .await?
+        .ok_or(ServiceError::NotFound)?;
+    let (audience, _) = application_model
+        .find_one_application_by_id(&create_params.audience)
+        .await?
+        .ok_or(ServiceError::NotFound)?;
+
+    if audience.creator_id != user_id_from_session.user_id {
+        return Err(ServiceError::PermissionDenied.into());
+    }
+
+    let policy_model = TokenExchangePolicyModel::new(&conn);
+    let policy = match policy_model
+        .find_one_policy(&url_params.application_id, &create_params.audience)
+        .await?
+    {
+        Some(policy) => policy,
+        None => {
+            policy_model
+                .insert_policy(&url_params.application_id, &create_params.audience)
+                .await?
+        }
+    };
+
+    let res = SuccessResponse {
+        token_exchange_policy: ResponseTokenExchangePolicy {
+            id: policy.id,
+            audience: policy.audience,
+            created_at: policy.created_at.to_string(),
+        },
+    };
+
+    Ok(OkResponse::new(res))
+}
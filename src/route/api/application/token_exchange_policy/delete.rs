@@ -0,0 +1,53 @@
+use axum::extract::{Extension, Path};
+use sea_orm::DatabaseConnection;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::{AppError, ServiceError},
+    extractor::user_id_from_session::UserIdFromSession,
+    model::{application::ApplicationModel, token_exchange_policy::TokenExchangePolicyModel},
+    response::OkResponse,
+};
+
+#[derive(Serialize)]
+pub struct SuccessResponse {}
+
+#[derive(Deserialize)]
+pub struct DeletePolicyUrlParams {
+    pub application_id: String,
+    pub policy_id: i32,
+}
+
+pub async fn handler(
+    Extension(conn): Extension<DatabaseConnection>,
+    Path(url_params): Path<DeletePolicyUrlParams>,
+    user_id_from_session: UserIdFromSession,
+) -> Result<OkResponse<SuccessResponse>, AppError> {
+    let application_model = ApplicationModel::new(&conn);
+    let (application, _) = application_model
+        .find_one_application_by_id(url_params.application_id.as_str())
+        .await?
+        .ok_or(ServiceError::NotFound)?;
+
+    let policy_model = TokenExchangePolicyModel::new(&conn);
+    let policy = policy_model
+        .find_one_policy_by_id(&url_params.policy_id)
+        .await?
+        .filter(|policy| policy.application_id == url_params.application_id)
+        .ok_or(ServiceError::NotFound)?;
+
+    // Both the exchanging application and the audience may drop the policy
+    let owns_audience = application_model
+        .find_one_application_by_id(&policy.audience)
+        .await?
+        .map_or(false, |(audience, _)| {
+            audience.creator_id == user_id_from_session.user_id
+        });
+    if application.creator_id != user_id_from_session.user_id && !owns_audience {
+        return Err(ServiceError::PermissionDenied.into());
+    }
+
+    policy_model.delete_policy(&policy.id).await?;
+
+    Ok(OkResponse::new(SuccessResponse {}))
+}
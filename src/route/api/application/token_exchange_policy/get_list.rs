@@ -0,0 +1,59 @@
+use axum::extract::{Extension, Path};
+use sea_orm::DatabaseConnection;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::{AppError, ServiceError},
+    extractor::user_id_from_session::UserIdFromSession,
+    model::{application::ApplicationModel, token_exchange_policy::TokenExchangePolicyModel},
+    response::OkResponse,
+};
+
+#[derive(Serialize)]
+struct ResponseTokenExchangePolicy {
+    id: i32,
+    audience: String,
+    created_at: String,
+}
+
+#[derive(Deserialize)]
+pub struct GetPoliciesListUrlParams {
+    pub application_id: String,
+}
+
+#[derive(Serialize)]
+pub struct SuccessResponse {
+    token_exchange_policies: Vec<ResponseTokenExchangePolicy>,
+}
+
+pub async fn handler(
+    Extension(conn): Extension<DatabaseConnection>,
+    Path(url_params): Path<GetPoliciesListUrlParams>,
+    user_id_from_session: UserIdFromSession,
+) -> Result<OkResponse<SuccessResponse>, AppError> {
+    let application = ApplicationModel::new(&conn)
+        .find_one_application_by_id(url_params.application_id.as_str())
+        .await?
+        .ok_or(ServiceError::NotFound)?;
+
+    if application.0.creator_id != user_id_from_session.user_id {
+        return Err(ServiceError::PermissionDenied.into());
+    }
+
+    let policies = TokenExchangePolicyModel::new(&conn)
+        .find_policies_by_application_id(&url_params.application_id)
+        .await?;
+
+    let res = SuccessResponse {
+        token_exchange_policies: policies
+            .into_iter()
+            .map(|policy| ResponseTokenExchangePolicy {
+                id: policy.id,
+                audience: policy.audience,
+                created_at: policy.created_at.to_string(),
+            })
+            .collect(),
+    };
+
+    Ok(OkResponse::new(res))
+}
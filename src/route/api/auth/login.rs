@@ -16,9 +16,12 @@ use crate::{
     constants::{PARSED_FRONTEND_URL, ROOT_DOMAIN, SESSION_COOKIE_KEY},
     error::{AppError, ServiceError},
     extractor::user_id_from_session::UserIdFromSession,
-    model::user::UserModel,
+    model::{
+        login_session::{CreateLoginSessionParams, LoginSessionModel},
+        user::UserModel,
+    },
     response::OkResponse,
-    util::{decrypt_rsa_content, extract_private_key, verify_password},
+    util::{decrypt_rsa_content, extract_private_key, verify_password, verify_return_to},
 };
 
 const SESSION_EXPIRES_TIME: u64 = 60 * 60 * 24 * 15;
@@ -38,10 +41,17 @@ pub struct LoginParams {
 
     #[validate(required, non_control_character)]
     rsa_token: Option<String>,
+
+    // Signed by the authorize endpoint when it sends the browser to login
+    #[validate(non_control_character)]
+    return_to: Option<String>,
 }
 
 #[derive(Serialize)]
-pub struct SuccessResponse {}
+pub struct SuccessResponse {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    return_to: Option<String>,
+}
 
 pub async fn handler(
     Extension(store): Extension<RedisSessionStore>,
@@ -53,6 +63,7 @@ pub async fn handler(
     let password = login_params.password.unwrap();
     let username = login_params.username.unwrap();
     let rsa_token = login_params.rsa_token.unwrap();
+    let return_to = login_params.return_to.as_deref().and_then(verify_return_to);
 
     let private_key = extract_private_key(&rsa_token, &store).await?;
     let password =
@@ -68,12 +79,29 @@ pub async fn handler(
         return Err(ServiceError::LoginFailed.into());
     }
 
+    let sid = uuid::Uuid::new_v4().to_string();
     let mut session = Session::new();
     session
-        .insert("user", UserIdFromSession { user_id: user.id })
+        .insert(
+            "user",
+            UserIdFromSession {
+                user_id: user.id,
+                auth_time: Some(chrono::Utc::now().timestamp()),
+                sid: Some(sid.clone()),
+            },
+        )
         .unwrap();
 
     session.expire_in(std::time::Duration::from_secs(SESSION_EXPIRES_TIME));
+
+    // The session id is the redis key, which lets an ended login session be removed later
+    LoginSessionModel::new(&conn)
+        .insert_session(CreateLoginSessionParams {
+            sid,
+            user_id: user.id,
+            session_id: session.id().to_string(),
+        })
+        .await?;
     let token = store.store_session(session).await?.unwrap();
 
     let cookie = Cookie::build((SESSION_COOKIE_KEY.as_str(), token))
@@ -81,7 +109,8 @@ pub async fn handler(
         .path("/")
         .domain(ROOT_DOMAIN.as_str())
         .http_only(true)
-        .same_site(cookie::SameSite::Strict)
+        // Lax so the session reaches authorize and end_session when a relying party navigates there
+        .same_site(cookie::SameSite::Lax)
         .max_age(cookie::time::Duration::seconds(
             SESSION_EXPIRES_TIME.try_into().unwrap(),
         ))
@@ -92,5 +121,10 @@ pub async fn handler(
         header::SET_COOKIE,
         HeaderValue::from_str(&cookie.to_string()).unwrap(),
     );
-    Ok((StatusCode::OK, headers, OkResponse::new(SuccessResponse {})).into_response())
+    Ok((
+        StatusCode::OK,
+        headers,
+        OkResponse::new(SuccessResponse { return_to }),
+    )
+        .into_response())
 }
@@ -6,42 +6,62 @@ use axum::{
 };
 use axum_extra::{headers::Cookie, TypedHeader};
 use http::{header, HeaderMap, HeaderValue, StatusCode};
+use sea_orm::DatabaseConnection;
 use serde::Serialize;
 
 use crate::{
     constants::{PARSED_FRONTEND_URL, ROOT_DOMAIN, SESSION_COOKIE_KEY},
     error::{AppError, ServiceError},
+    extractor::user_id_from_session::UserIdFromSession,
     response::OkResponse,
+    route::api::oidc::{
+        backchannel_logout::end_login_session, frontchannel_logout::frontchannel_logout_urls,
+        keys::OidcKeys,
+    },
 };
 
 #[derive(Serialize)]
-pub struct SuccessResponse {}
+pub struct SuccessResponse {
+    // The front-end loads these in iframes so the applications can clear their own state
+    frontchannel_logout_urls: Vec<String>,
+}
 
-fn logout_cookie() -> cookie::Cookie<'static> {
+pub fn logout_cookie() -> cookie::Cookie<'static> {
     cookie::Cookie::build((SESSION_COOKIE_KEY.as_str().to_owned(), "logout"))
         .secure(PARSED_FRONTEND_URL.scheme().eq("https"))
         .path("/")
         .domain(ROOT_DOMAIN.as_str())
         .http_only(true)
-        .same_site(cookie::SameSite::Strict)
+        .same_site(cookie::SameSite::Lax)
         .max_age(cookie::time::Duration::seconds(0))
         .build()
 }
 
 pub async fn handler(
     Extension(store): Extension<RedisSessionStore>,
+    Extension(conn): Extension<DatabaseConnection>,
+    Extension(oidc_keys): Extension<OidcKeys>,
     cookie: TypedHeader<Cookie>,
 ) -> Result<Response, AppError> {
     let session_cookie = &cookie
         .get(SESSION_COOKIE_KEY.as_str())
         .ok_or(ServiceError::LoginRequired)?;
 
-    let mut session = store
+    let session = store
         .load_session(session_cookie.to_string())
         .await?
         .ok_or(ServiceError::LoginRequired)?;
 
-    session.destroy();
+    let sid = session
+        .get::<UserIdFromSession>("user")
+        .and_then(|user| user.sid);
+    store.destroy_session(session).await?;
+
+    let mut logout_urls = Vec::new();
+    if let Some(sid) = sid {
+        end_login_session(&conn, &oidc_keys, &sid).await?;
+        logout_urls = frontchannel_logout_urls(&conn, &sid).await?;
+    }
 
     let cookie = logout_cookie();
 
@@ -51,7 +71,14 @@ pub async fn handler(
         HeaderValue::from_str(&cookie.to_string()).unwrap(),
     );
 
-    Ok((StatusCode::OK, headers, OkResponse::new(SuccessResponse {})).into_response())
+    Ok((
+        StatusCode::OK,
+        headers,
+        OkResponse::new(SuccessResponse {
+            frontchannel_logout_urls: logout_urls.iter().map(|url| url.to_string()).collect(),
+        }),
+    )
+        .into_response())
 }
 
 #[cfg(test)]
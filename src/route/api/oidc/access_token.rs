@@ -0,0 +1,36 @@
+use serde::{Deserialize, Serialize};
+
+use super::{dpop::Confirmation, keys::OidcKeys, token_exchange::Actor};
+
+// RFC 9068 media type for JWT access tokens, carried in the `typ` header
+pub const ACCESS_TOKEN_JWT_TYPE: &str = "at+jwt";
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AccessTokenClaims {
+    pub iss: String,
+    pub sub: String,
+    pub aud: String,
+    pub exp: usize,
+    pub iat: usize,
+    pub jti: String,
+    pub client_id: String,
+    pub scope: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub auth_time: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub act: Option<Actor>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cnf: Option<Confirmation>,
+}
+
+// Token rows are keyed by the opaque value, or by the `jti` for JWT access
+// tokens. A JWT that does not verify against our keys cannot match any row.
+pub fn access_token_lookup_key(oidc_keys: &OidcKeys, presented: &str) -> String {
+    if presented.split('.').count() != 3 {
+        return presented.to_string();
+    }
+
+    oidc_keys
+        .verify::<AccessTokenClaims>(presented)
+        .map_or_else(|_| String::new(), |claims| claims.jti)
+}
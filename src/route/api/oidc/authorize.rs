@@ -1,111 +1,738 @@
 use std::collections::HashSet;
 
+use async_redis_session::RedisSessionStore;
 use axum::{
-    extract::{Extension, Query},
-    http::StatusCode,
-    response::{IntoResponse, Redirect},
+    extract::{Extension, OriginalUri, Query},
+    http::Uri,
+    response::{IntoResponse, Redirect, Response},
 };
+use base64::prelude::*;
+use openssl::{error::ErrorStack, memcmp};
 use sea_orm::{ActiveModelTrait, ColumnTrait, EntityTrait, QueryFilter, Set};
-use serde::Deserialize;
+use serde::{Deserialize, Deserializer, Serialize};
 use uuid::Uuid;
 
 use entity::{application, authorization_code};
 
-// A basic error type for this handler
-#[derive(Debug)]
-pub struct AuthError(StatusCode, String);
+use super::{
+    client_auth::is_public_client,
+    consent::ConsentRequest,
+    id_token::verify_id_token_hint,
+    issue::{issue_tokens, sign_id_token, IdTokenParams, IssueTokenParams, OFFLINE_ACCESS_SCOPE},
+    keys::OidcKeys,
+    par::{consume_request_uri, extend_request_uri, is_pushed_request_uri, resolve_request_uri},
+    pkce::is_valid_code_challenge,
+    request_object::{fetch_request_object, verify_request_object},
+    response::{
+        authorization_response, default_response_mode, is_valid_response_mode,
+        normalize_response_type, response_type_allowed, response_type_includes, RESPONSE_MODES,
+        RESPONSE_MODE_QUERY, RESPONSE_TYPES, RESPONSE_TYPE_CODE, RESPONSE_TYPE_ID_TOKEN,
+        RESPONSE_TYPE_TOKEN,
+    },
+    scope::{parse_scope, scopes_from_json, scopes_to_json, Scopes},
+};
+use crate::{
+    constants::PARSED_FRONTEND_URL,
+    error::{AppError, OAuthError},
+    extractor::user_id_from_session::UserIdFromSession,
+    model::{
+        application_access_grant::ApplicationAccessGrantModel, login_session::record_participant,
+    },
+    util::{hmac_sha256, sign_return_to},
+};
+
+pub const PROMPT_VALUES: [&str; 4] = ["none", "login", "consent", "select_account"];
+// Added to the authorize path when the user is sent to log in or consent, so
+// the resumed request can tell that `prompt` and `max_age` have been honored
+const PROMPTED_AT_PARAM: &str = "prompted_at";
+const PROMPTED_AT_EXPIRES_IN: i64 = 10 * 60;
+
+// Where errors go once the redirect_uri is known to belong to the client
+struct ErrorRedirect {
+    redirect_uri: url::Url,
+    response_mode: String,
+    state: Option<String>,
+}
+
+// An authorization request failure. Until the redirect_uri has been checked the
+// error is shown to the user; after that it is sent back to the client.
+pub struct AuthorizationError {
+    pub error: OAuthError,
+    redirect: Option<ErrorRedirect>,
+}
 
-impl IntoResponse for AuthError {
-    fn into_response(self) -> axum::response::Response {
-        (self.0, self.1).into_response()
+impl From<OAuthError> for AuthorizationError {
+    fn from(error: OAuthError) -> Self {
+        Self {
+            error,
+            redirect: None,
+        }
     }
 }
 
-impl From<sea_orm::DbErr> for AuthError {
-    fn from(err: sea_orm::DbErr) -> Self {
-        eprintln!("Database error: {:?}", err);
-        AuthError(
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "Database error".to_string(),
-        )
+impl IntoResponse for AuthorizationError {
+    fn into_response(self) -> Response {
+        let Some(redirect) = self.redirect else {
+            return self.error.into_response();
+        };
+
+        let mut params = vec![("error", self.error.error().to_string())];
+        if let Some(description) = self.error.description() {
+            params.push(("error_description", description.to_string()));
+        }
+        if let Some(state) = redirect.state {
+            params.push(("state", state));
+        }
+
+        authorization_response(redirect.redirect_uri, &redirect.response_mode, &params)
+    }
+}
+
+fn invalid_request(description: &str) -> OAuthError {
+    OAuthError::bad_request("invalid_request").with_description(description)
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct AuthorizationParams {
+    pub response_type: Option<String>,
+    pub redirect_uri: Option<String>,
+    pub scope: Option<String>,
+    pub state: Option<String>,
+    pub nonce: Option<String>,
+    pub code_challenge: Option<String>,
+    pub code_challenge_method: Option<String>,
+    pub response_mode: Option<String>,
+    pub prompt: Option<String>,
+    #[serde(default, deserialize_with = "string_or_number")]
+    pub max_age: Option<String>,
+    pub login_hint: Option<String>,
+    pub id_token_hint: Option<String>,
+}
+
+// `max_age` is a string in a query but usually a number in a request object
+fn string_or_number<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<String>, D::Error> {
+    Ok(
+        match Option::<serde_json::Value>::deserialize(deserializer)? {
+            Some(serde_json::Value::String(value)) => Some(value),
+            Some(serde_json::Value::Number(value)) => Some(value.to_string()),
+            _ => None,
+        },
+    )
+}
+
+impl AuthorizationParams {
+    // Parameters from a request object take precedence over the plain ones
+    pub fn overridden_by(self, request: AuthorizationParams) -> Self {
+        Self {
+            response_type: request.response_type.or(self.response_type),
+            redirect_uri: request.redirect_uri.or(self.redirect_uri),
+            scope: request.scope.or(self.scope),
+            state: request.state.or(self.state),
+            nonce: request.nonce.or(self.nonce),
+            code_challenge: request.code_challenge.or(self.code_challenge),
+            code_challenge_method: request.code_challenge_method.or(self.code_challenge_method),
+            response_mode: request.response_mode.or(self.response_mode),
+            prompt: request.prompt.or(self.prompt),
+            max_age: request.max_age.or(self.max_age),
+            login_hint: request.login_hint.or(self.login_hint),
+            id_token_hint: request.id_token_hint.or(self.id_token_hint),
+        }
     }
 }
 
 #[derive(Debug, Deserialize)]
 pub struct AuthorizeQuery {
-    response_type: String,
     client_id: String,
-    redirect_uri: String,
-    scope: String,
-    state: Option<String>,
-    #[allow(dead_code)]
-    nonce: Option<String>,
+    // A signed request object carrying the parameters
+    request: Option<String>,
+    // Refers to parameters pushed to the PAR endpoint, which replace the query,
+    // or to a request object published by the client
+    request_uri: Option<String>,
+    // Set by this endpoint when it resumes after login or consent
+    prompted_at: Option<String>,
+    // Set by the consent API when the user declines
+    consent_denied: Option<String>,
+    #[serde(flatten)]
+    params: AuthorizationParams,
+}
+
+// Authorization parameters that passed validation against the application
+pub struct ValidatedAuthorization {
+    // Normalized, e.g. `code id_token`
+    pub response_type: String,
+    pub response_mode: String,
+    pub redirect_uri: String,
+    pub scopes: Scopes,
+    pub state: Option<String>,
+    pub nonce: Option<String>,
+    pub code_challenge: Option<String>,
+    pub code_challenge_method: Option<String>,
+    pub prompt: HashSet<String>,
+    pub max_age: Option<i64>,
+    pub login_hint: Option<String>,
+    pub id_token_hint: Option<String>,
+}
+
+impl ValidatedAuthorization {
+    // Sends the error back to the client the way a response would be
+    pub fn error(&self, error: OAuthError) -> AuthorizationError {
+        AuthorizationError {
+            error,
+            redirect: url::Url::parse(&self.redirect_uri)
+                .ok()
+                .map(|redirect_uri| ErrorRedirect {
+                    redirect_uri,
+                    response_mode: self.response_mode.clone(),
+                    state: self.state.clone(),
+                }),
+        }
+    }
+}
+
+// Shared by the authorize and PAR endpoints
+pub fn validate_authorization_params(
+    app: &application::Model,
+    params: AuthorizationParams,
+) -> Result<ValidatedAuthorization, AuthorizationError> {
+    // Nothing is sent to a redirect_uri that is not registered for the application
+    let redirect_uri = params
+        .redirect_uri
+        .ok_or_else(|| invalid_request("Missing redirect_uri"))?;
+    let redirect_uris: HashSet<String> = serde_json::from_str(&app.redirect_uris).unwrap();
+    if !redirect_uris.contains(&redirect_uri) {
+        return Err(invalid_request("Unregistered redirect_uri").into());
+    }
+    let parsed_redirect_uri =
+        url::Url::parse(&redirect_uri).map_err(|_| invalid_request("Invalid redirect_uri"))?;
+
+    // Errors use the requested response_mode when it is a known one
+    let response_type = params.response_type.as_deref().map(normalize_response_type);
+    let error_response_mode = params
+        .response_mode
+        .clone()
+        .filter(|response_mode| RESPONSE_MODES.contains(&response_mode.as_str()))
+        .unwrap_or_else(|| {
+            response_type
+                .as_deref()
+                .map_or(RESPONSE_MODE_QUERY, default_response_mode)
+                .to_string()
+        });
+    let redirect_error = |error: OAuthError| AuthorizationError {
+        error,
+        redirect: Some(ErrorRedirect {
+            redirect_uri: parsed_redirect_uri.clone(),
+            response_mode: error_response_mode.clone(),
+            state: params.state.clone(),
+        }),
+    };
+
+    let (response_type, scope) = match (response_type, params.scope) {
+        (Some(response_type), Some(scope)) => (response_type, scope),
+        (None, _) => return Err(redirect_error(invalid_request("Missing response_type"))),
+        (_, None) => return Err(redirect_error(invalid_request("Missing scope"))),
+    };
+
+    // Validate the response_type against the ones the application may use
+    if !RESPONSE_TYPES.contains(&response_type.as_str()) {
+        return Err(redirect_error(OAuthError::bad_request(
+            "unsupported_response_type",
+        )));
+    }
+    if !response_type_allowed(app, &response_type) {
+        return Err(redirect_error(OAuthError::bad_request(
+            "unauthorized_client",
+        )));
+    }
+
+    let response_mode = params
+        .response_mode
+        .unwrap_or_else(|| default_response_mode(&response_type).to_string());
+    if !is_valid_response_mode(&response_type, &response_mode) {
+        return Err(redirect_error(invalid_request(
+            "Unsupported response_mode for the response_type",
+        )));
+    }
+
+    // An ID token sent through the browser is tied to the request by its nonce
+    let scopes = parse_scope(&scope);
+    if response_type_includes(&response_type, RESPONSE_TYPE_ID_TOKEN)
+        && (params.nonce.is_none() || !scopes.contains("openid"))
+    {
+        return Err(redirect_error(invalid_request(
+            "An id_token response requires a nonce and the openid scope",
+        )));
+    }
+
+    // `none` asks for no interaction at all, so it cannot be combined with the others
+    let prompt: HashSet<String> = params
+        .prompt
+        .as_deref()
+        .map(|prompt| prompt.split_whitespace().map(str::to_string).collect())
+        .unwrap_or_default();
+    if !prompt
+        .iter()
+        .all(|value| PROMPT_VALUES.contains(&value.as_str()))
+        || (prompt.contains("none") && prompt.len() > 1)
+    {
+        return Err(redirect_error(invalid_request("Invalid prompt")));
+    }
+
+    let max_age = match params.max_age {
+        Some(max_age) => match max_age.parse::<i64>() {
+            Ok(max_age) if max_age >= 0 => Some(max_age),
+            _ => return Err(redirect_error(invalid_request("Invalid max_age"))),
+        },
+        None => None,
+    };
+
+    // Validate the PKCE challenge, which defaults to the plain method
+    let code_challenge_method = params.code_challenge.as_ref().map(|_| {
+        params
+            .code_challenge_method
+            .clone()
+            .unwrap_or("plain".to_string())
+    });
+    if let (Some(challenge), Some(method)) = (&params.code_challenge, &code_challenge_method) {
+        if !is_valid_code_challenge(challenge, method) {
+            return Err(redirect_error(invalid_request("Invalid code_challenge")));
+        }
+    } else if response_type_includes(&response_type, RESPONSE_TYPE_CODE)
+        && (app.require_pkce != 0 || is_public_client(app))
+    {
+        return Err(redirect_error(invalid_request("code_challenge required")));
+    }
+
+    Ok(ValidatedAuthorization {
+        response_type,
+        response_mode,
+        redirect_uri,
+        scopes,
+        state: params.state,
+        nonce: params.nonce,
+        code_challenge: params.code_challenge,
+        code_challenge_method,
+        prompt,
+        max_age,
+        login_hint: params.login_hint,
+        id_token_hint: params.id_token_hint,
+    })
+}
+
+fn prompted_at_signature(client_id: &str, prompted_at: i64) -> Result<Vec<u8>, ErrorStack> {
+    hmac_sha256(format!("{}:{}:{}", PROMPTED_AT_PARAM, client_id, prompted_at).as_bytes())
+}
+
+fn sign_prompted_at(client_id: &str, prompted_at: i64) -> Result<String, ErrorStack> {
+    Ok(format!(
+        "{}.{}",
+        prompted_at,
+        BASE64_URL_SAFE_NO_PAD.encode(prompted_at_signature(client_id, prompted_at)?)
+    ))
+}
+
+// The time the user was last sent to log in or consent for this request, as
+// long as it is recent and was set by us.
+fn verify_prompted_at(client_id: &str, value: &str) -> Option<i64> {
+    let (prompted_at, signature) = value.split_once('.')?;
+    let prompted_at = prompted_at.parse::<i64>().ok()?;
+    let signature = BASE64_URL_SAFE_NO_PAD.decode(signature).ok()?;
+    let expected = prompted_at_signature(client_id, prompted_at).ok()?;
+
+    if signature.len() != expected.len() || !memcmp::eq(&signature, &expected) {
+        return None;
+    }
+
+    let age = chrono::Utc::now().timestamp() - prompted_at;
+    (0..=PROMPTED_AT_EXPIRES_IN)
+        .contains(&age)
+        .then_some(prompted_at)
+}
+
+// The authorize path to come back to, carrying `prompted_at`
+fn resume_path(uri: &Uri, prompted_at: &str) -> String {
+    let mut query = url::form_urlencoded::Serializer::new(String::new());
+    if let Some(current) = uri.query() {
+        query.extend_pairs(
+            url::form_urlencoded::parse(current.as_bytes())
+                .filter(|(name, _)| name != PROMPTED_AT_PARAM),
+        );
+    }
+    query.append_pair(PROMPTED_AT_PARAM, prompted_at);
+
+    format!("{}?{}", uri.path(), query.finish())
+}
+
+fn front_end_url(path: &str) -> Result<url::Url, AppError> {
+    PARSED_FRONTEND_URL
+        .join(path)
+        .map_err(|err| AppError::UnexpectedError(err.into()))
+}
+
+// Send the browser to the front-end login page, which hands `return_to` back to
+// the login API and follows it once the session cookie is set.
+fn login_redirect(return_path: &str, login_hint: Option<&str>) -> Result<Redirect, AppError> {
+    let mut login_url = front_end_url("login")?;
+    login_url
+        .query_pairs_mut()
+        .append_pair("return_to", &sign_return_to(return_path)?);
+    if let Some(login_hint) = login_hint {
+        login_url
+            .query_pairs_mut()
+            .append_pair("login_hint", login_hint);
+    }
+
+    Ok(Redirect::to(login_url.as_str()))
+}
+
+fn consent_redirect(consent_id: &str) -> Result<Redirect, AppError> {
+    let mut consent_url = front_end_url("consent")?;
+    consent_url
+        .query_pairs_mut()
+        .append_pair("consent_id", consent_id);
+
+    Ok(Redirect::to(consent_url.as_str()))
+}
+
+fn request_object_error(err: AppError) -> OAuthError {
+    match err {
+        AppError::ServiceError(_) => OAuthError::bad_request("invalid_request_object"),
+        err => err.into(),
+    }
 }
 
 pub async fn handler(
     Extension(conn): Extension<sea_orm::DatabaseConnection>,
+    Extension(store): Extension<RedisSessionStore>,
+    Extension(redis_client): Extension<redis::Client>,
+    Extension(oidc_keys): Extension<OidcKeys>,
+    user_id_from_session: Option<UserIdFromSession>,
+    OriginalUri(uri): OriginalUri,
     Query(query): Query<AuthorizeQuery>,
-) -> Result<impl IntoResponse, AuthError> {
-    // Validate response_type is "code"
-    if query.response_type != "code" {
-        return Err(AuthError(
-            StatusCode::BAD_REQUEST,
-            "unsupported_response_type".to_string(),
-        ));
-    }
-
-    // Fetch the application by client_id
+) -> Result<Response, OAuthError> {
+    // Fetch the application by client_id. Without one there is nowhere to
+    // redirect to, so errors up to the redirect_uri check are shown directly.
     let app = application::Entity::find()
         .filter(application::Column::Id.eq(query.client_id.clone()))
         .one(&conn)
         .await?
-        .ok_or_else(|| AuthError(StatusCode::BAD_REQUEST, "invalid_client".to_string()))?;
+        .ok_or_else(|| invalid_request("Unknown client_id"))?;
 
-    // Validate the redirect_uri
-    let redirect_uris: HashSet<String> = serde_json::from_str(&app.redirect_uris).unwrap();
-    if !redirect_uris.contains(&query.redirect_uri) {
-        return Err(AuthError(
-            StatusCode::BAD_REQUEST,
-            "invalid_redirect_uri".to_string(),
+    if query.request.is_some() && query.request_uri.is_some() {
+        return Err(invalid_request(
+            "request and request_uri cannot be used together",
+        ));
+    }
+    let pushed_request_uri = query
+        .request_uri
+        .as_deref()
+        .filter(|request_uri| is_pushed_request_uri(request_uri));
+
+    let params = if let Some(request_uri) = pushed_request_uri {
+        resolve_request_uri(&redis_client, &app.id, request_uri)
+            .await?
+            .ok_or_else(|| OAuthError::bad_request("invalid_request_uri"))?
+    } else if app.require_pushed_authorization_requests != 0 {
+        return Err(invalid_request(
+            "The client must use pushed authorization requests",
         ));
+    } else {
+        let request_object = match (query.request, &query.request_uri) {
+            (Some(request), _) => Some(request),
+            (None, Some(request_uri)) => Some(
+                fetch_request_object(&app, request_uri)
+                    .await
+                    .map_err(|_| OAuthError::bad_request("invalid_request_uri"))?,
+            ),
+            (None, None) => None,
+        };
+
+        match request_object {
+            Some(request) => {
+                let request = verify_request_object(&conn, &redis_client, &app, &request)
+                    .await
+                    .map_err(request_object_error)?;
+                query.params.overridden_by(request)
+            }
+            None => query.params,
+        }
+    };
+    let request = match validate_authorization_params(&app, params) {
+        Ok(request) => request,
+        Err(err) => return Ok(err.into_response()),
+    };
+
+    // The hint names the user the client expects to be signed in
+    let hinted_user = match &request.id_token_hint {
+        Some(id_token_hint) => match verify_id_token_hint(&oidc_keys, id_token_hint)
+            .ok()
+            .filter(|claims| claims.aud == app.id)
+        {
+            Some(claims) => Some(claims.sub),
+            None => {
+                return Ok(request
+                    .error(invalid_request("Invalid id_token_hint"))
+                    .into_response());
+            }
+        },
+        None => None,
+    };
+
+    // A prompt from an earlier round trip is kept so it covers both login and consent
+    let prompted_at = query
+        .prompted_at
+        .as_deref()
+        .and_then(|prompted_at| verify_prompted_at(&app.id, prompted_at));
+    let return_path = resume_path(
+        &uri,
+        &match prompted_at {
+            Some(prompted_at) => sign_prompted_at(&app.id, prompted_at)?,
+            None => sign_prompted_at(&app.id, chrono::Utc::now().timestamp())?,
+        },
+    );
+
+    // Without a fresh enough login session for the expected user, the user has
+    // to sign in first and come back here
+    let session = user_id_from_session.filter(|session| {
+        let auth_time = session.auth_time.unwrap_or(0);
+        let reauthenticated = prompted_at.map_or(false, |prompted_at| auth_time >= prompted_at);
+        let expired = request.max_age.map_or(false, |max_age| {
+            chrono::Utc::now().timestamp() - auth_time > max_age
+        });
+
+        (reauthenticated || (!request.prompt.contains("login") && !expired))
+            && hinted_user
+                .as_ref()
+                .map_or(true, |sub| *sub == session.user_id.to_string())
+    });
+    let (user_id, auth_time, sid) = match session {
+        Some(session) => (session.user_id, session.auth_time, session.sid),
+        None if request.prompt.contains("none") => {
+            return Ok(request
+                .error(OAuthError::bad_request("login_required"))
+                .into_response());
+        }
+        None => {
+            if let Some(request_uri) = &query.request_uri {
+                extend_request_uri(&redis_client, request_uri).await?;
+            }
+            return Ok(login_redirect(&return_path, request.login_hint.as_deref())?.into_response());
+        }
+    };
+
+    // The user turned the request down on the consent page
+    if query.consent_denied.is_some() {
+        return Ok(request
+            .error(
+                OAuthError::bad_request("access_denied")
+                    .with_description("The user denied the request"),
+            )
+            .into_response());
+    }
+
+    // Third-party apps need the user's approval for every scope they ask for,
+    // and `prompt=consent` asks every app for it again. A consent given after
+    // the prompt only settles the latter: `prompted_at` is readable by the
+    // client, so it never stands in for the granted scopes.
+    let grant = ApplicationAccessGrantModel::new(&conn)
+        .find_one_grant(&user_id, &app.id)
+        .await?;
+    let consented = prompted_at.map_or(false, |prompted_at| {
+        grant.as_ref().map_or(false, |grant| {
+            grant.updated_at.and_utc().timestamp() >= prompted_at
+        })
+    });
+    let granted_scopes = grant
+        .map(|grant| scopes_from_json(&grant.scopes))
+        .unwrap_or_default();
+    let scopes_granted = granted_scopes.is_superset(&request.scopes);
+
+    if (request.prompt.contains("consent") && !(consented && scopes_granted))
+        || (app.first_party == 0 && !scopes_granted)
+    {
+        if request.prompt.contains("none") {
+            return Ok(request
+                .error(OAuthError::bad_request("consent_required"))
+                .into_response());
+        }
+
+        let consent_id = ConsentRequest {
+            user_id,
+            client_id: app.id,
+            scopes: request.scopes,
+            return_to: return_path,
+        }
+        .store(&store)
+        .await?;
+        if let Some(request_uri) = &query.request_uri {
+            extend_request_uri(&redis_client, request_uri).await?;
+        }
+
+        return Ok(consent_redirect(&consent_id)?.into_response());
     }
 
-    // Assume user is authenticated and get user ID (hardcoded for now)
-    let user_id = 1;
+    let requested_scopes = request.scopes;
 
-    // Generate a new authorization code
-    let code = Uuid::new_v4().to_string();
+    let auth_time = auth_time
+        .and_then(|auth_time| chrono::DateTime::from_timestamp(auth_time, 0))
+        .map(|auth_time| auth_time.naive_utc());
+    let mut response_params: Vec<(&str, String)> = Vec::new();
 
-    // ... (previous code)
+    let code = if response_type_includes(&request.response_type, RESPONSE_TYPE_CODE) {
+        // Generate a new authorization code
+        let code = Uuid::new_v4().to_string();
+        let now = chrono::Utc::now().naive_utc();
 
-    // Create and save the authorization_code model
-    let new_code = authorization_code::ActiveModel {
-        code: Set(code.clone()),
-        user_id: Set(user_id),
-        application_id: Set(app.id),
-        scopes: Set(query.scope),
-        redirect_uri: Set(query.redirect_uri.clone()),
-        expires_at: Set(chrono::Utc::now().naive_utc() + chrono::Duration::minutes(10)),
-        ..Default::default()
+        // Create and save the authorization_code model
+        let new_code = authorization_code::ActiveModel {
+            code: Set(code.clone()),
+            user_id: Set(user_id),
+            application_id: Set(app.id.clone()),
+            scopes: Set(scopes_to_json(&requested_scopes)),
+            redirect_uri: Set(request.redirect_uri.clone()),
+            expires_at: Set(now + chrono::Duration::minutes(10)),
+            created_at: Set(now),
+            code_challenge: Set(request.code_challenge),
+            code_challenge_method: Set(request.code_challenge_method),
+            nonce: Set(request.nonce.clone()),
+            auth_time: Set(auth_time),
+            sid: Set(sid.clone()),
+        };
+
+        new_code.insert(&conn).await?;
+        response_params.push(("code", code.clone()));
+        Some(code)
+    } else {
+        None
     };
 
-    new_code.insert(&conn).await?;
+    if response_type_includes(&request.response_type, RESPONSE_TYPE_TOKEN) {
+        // Tokens handed to the browser never come with a refresh token
+        let mut scopes = requested_scopes.clone();
+        scopes.remove(OFFLINE_ACCESS_SCOPE);
+        let tokens = issue_tokens(
+            &conn,
+            &oidc_keys,
+            IssueTokenParams {
+                app: &app,
+                user_id: Some(user_id),
+                scopes,
+                auth_time,
+                nonce: None,
+                family_id: None,
+                sid: sid.clone(),
+                audience: None,
+                act: None,
+                dpop_jkt: None,
+            },
+        )
+        .await?;
 
-    // Construct the redirect URL
-    let mut redirect_url = url::Url::parse(&query.redirect_uri).map_err(|_| {
-        AuthError(
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "invalid redirect_uri format".to_string(),
+        response_params.push(("access_token", tokens.access_token));
+        response_params.push(("token_type", tokens.token_type));
+        response_params.push(("expires_in", tokens.expires_in.to_string()));
+    }
+
+    if response_type_includes(&request.response_type, RESPONSE_TYPE_ID_TOKEN) {
+        if let Some(sid) = &sid {
+            record_participant(&conn, sid, &app.id).await?;
+        }
+        let id_token = sign_id_token(
+            &conn,
+            &oidc_keys,
+            IdTokenParams {
+                app: &app,
+                user_id,
+                scopes: &requested_scopes,
+                auth_time,
+                nonce: request.nonce,
+                sid,
+                access_token: None,
+                code: code.as_deref(),
+            },
         )
-    })?;
+        .await?;
 
-    redirect_url.query_pairs_mut().append_pair("code", &code);
-    if let Some(state_val) = query.state {
-        redirect_url
-            .query_pairs_mut()
-            .append_pair("state", &state_val);
+        response_params.push(("id_token", id_token));
+    }
+
+    if let Some(state_val) = request.state {
+        response_params.push(("state", state_val));
+    }
+
+    if let Some(request_uri) = &query.request_uri {
+        consume_request_uri(&redis_client, request_uri).await?;
     }
 
-    // Return a redirect response
-    Ok(Redirect::to(redirect_url.as_str()))
+    let redirect_url = url::Url::parse(&request.redirect_uri)
+        .map_err(|err| AppError::UnexpectedError(err.into()))?;
+
+    Ok(authorization_response(
+        redirect_url,
+        &request.response_mode,
+        &response_params,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{
+        http::{header::LOCATION, Uri},
+        response::IntoResponse,
+    };
+
+    use super::{
+        invalid_request, resume_path, AuthorizationError, AuthorizationParams, ErrorRedirect,
+    };
+
+    #[test]
+    fn resume_path_replaces_prompted_at() {
+        let uri: Uri = "/api/oidc/authorize?client_id=app&prompted_at=1.old&prompt=login"
+            .parse()
+            .unwrap();
+
+        assert_eq!(
+            resume_path(&uri, "2.new"),
+            "/api/oidc/authorize?client_id=app&prompt=login&prompted_at=2.new"
+        );
+    }
+
+    #[test]
+    fn request_object_overrides_query() {
+        let query = AuthorizationParams {
+            response_type: Some("code".to_string()),
+            scope: Some("openid".to_string()),
+            state: Some("from-query".to_string()),
+            ..Default::default()
+        };
+        let request = AuthorizationParams {
+            scope: Some("openid email".to_string()),
+            state: Some("from-request".to_string()),
+            ..Default::default()
+        };
+
+        let params = query.overridden_by(request);
+        assert_eq!(params.response_type.as_deref(), Some("code"));
+        assert_eq!(params.scope.as_deref(), Some("openid email"));
+        assert_eq!(params.state.as_deref(), Some("from-request"));
+    }
+
+    #[test]
+    fn errors_go_back_with_state() {
+        let response = AuthorizationError {
+            error: invalid_request("Invalid prompt"),
+            redirect: Some(ErrorRedirect {
+                redirect_uri: "https://client.example/cb".parse().unwrap(),
+                response_mode: "fragment".to_string(),
+                state: Some("xyz".to_string()),
+            }),
+        }
+        .into_response();
+
+        assert_eq!(
+            response.headers()[LOCATION],
+            "https://client.example/cb#error=invalid_request&error_description=Invalid+prompt&state=xyz"
+        );
+    }
 }
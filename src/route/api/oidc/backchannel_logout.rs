@@ -0,0 +1,195 @@
+use std::time::Duration;
+
+use axum::http::{header::CONTENT_TYPE, Method, Request};
+use chrono::Utc;
+use http_body_util::Full;
+use hyper::body::Bytes;
+use sea_orm::DatabaseConnection;
+use serde::Serialize;
+use tokio::task::JoinHandle;
+use uuid::Uuid;
+
+use entity::{application, backchannel_logout_delivery, login_session};
+
+use super::keys::{signing_algorithm, OidcKeys};
+use crate::{
+    constants::PARSED_FRONTEND_URL,
+    error::AppError,
+    http_client::CLIENT_INSTANCE,
+    model::{
+        backchannel_logout_delivery::{
+            BackchannelLogoutDeliveryModel, CreateDeliveryParams, DELIVERY_STATUS_DELIVERED,
+            DELIVERY_STATUS_FAILED, DELIVERY_STATUS_PENDING,
+        },
+        login_session::LoginSessionModel,
+    },
+    storage::redis::delete_key,
+};
+
+pub const BACKCHANNEL_LOGOUT_EVENT: &str = "http://schemas.openid.net/event/backchannel-logout";
+const LOGOUT_TOKEN_JWT_TYPE: &str = "logout+jwt";
+// Long enough to cover every retry of a delivery
+const LOGOUT_TOKEN_EXPIRES_IN: i64 = 120;
+const DELIVERY_ATTEMPTS: i32 = 3;
+const DELIVERY_RETRY_DELAY: Duration = Duration::from_secs(5);
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Serialize)]
+struct LogoutTokenClaims {
+    iss: String,
+    sub: String,
+    aud: String,
+    iat: usize,
+    exp: usize,
+    jti: String,
+    sid: String,
+    events: serde_json::Value,
+}
+
+fn logout_token(
+    oidc_keys: &OidcKeys,
+    app: &application::Model,
+    session: &login_session::Model,
+) -> Result<String, AppError> {
+    let now = Utc::now();
+    let claims = LogoutTokenClaims {
+        iss: PARSED_FRONTEND_URL.to_string(),
+        sub: session.user_id.to_string(),
+        aud: app.id.clone(),
+        iat: now.timestamp() as usize,
+        exp: (now.timestamp() + LOGOUT_TOKEN_EXPIRES_IN) as usize,
+        jti: Uuid::new_v4().to_string(),
+        sid: session.sid.clone(),
+        events: serde_json::json!({ BACKCHANNEL_LOGOUT_EVENT: {} }),
+    };
+
+    oidc_keys.sign_with_type(
+        signing_algorithm(app.id_token_signed_response_alg.as_deref()),
+        LOGOUT_TOKEN_JWT_TYPE,
+        &claims,
+    )
+}
+
+async fn post_logout_token(logout_uri: &str, logout_token: &str) -> Result<(), String> {
+    let body = url::form_urlencoded::Serializer::new(String::new())
+        .append_pair("logout_token", logout_token)
+        .finish();
+    let request = Request::builder()
+        .method(Method::POST)
+        .uri(logout_uri)
+        .header(CONTENT_TYPE, "application/x-www-form-urlencoded")
+        .body(Full::new(Bytes::from(body)))
+        .map_err(|err| err.to_string())?;
+
+    let res = tokio::time::timeout(DELIVERY_TIMEOUT, CLIENT_INSTANCE.request(request))
+        .await
+        .map_err(|_| "timed out".to_string())?
+        .map_err(|err| err.to_string())?;
+
+    match res.status().is_success() {
+        true => Ok(()),
+        false => Err(format!("unexpected status {}", res.status())),
+    }
+}
+
+// Retry with a doubling delay, logging every attempt on the delivery row.
+async fn deliver(
+    conn: DatabaseConnection,
+    delivery: backchannel_logout_delivery::Model,
+    logout_token: String,
+) {
+    let delivery_model = BackchannelLogoutDeliveryModel::new(&conn);
+    let mut delay = DELIVERY_RETRY_DELAY;
+
+    for attempt in 1..=DELIVERY_ATTEMPTS {
+        let result = post_logout_token(&delivery.logout_uri, &logout_token).await;
+        let status = match &result {
+            Ok(_) => DELIVERY_STATUS_DELIVERED,
+            Err(_) if attempt == DELIVERY_ATTEMPTS => DELIVERY_STATUS_FAILED,
+            Err(_) => DELIVERY_STATUS_PENDING,
+        };
+
+        if let Err(err) = &result {
+            tracing::warn!(
+                "Back-channel logout to {} failed (attempt {}): {}",
+                delivery.logout_uri,
+                attempt,
+                err
+            );
+        }
+        if let Err(err) = delivery_model
+            .record_attempt(delivery.id, attempt, status, result.as_ref().err().cloned())
+            .await
+        {
+            tracing::error!("Recording back-channel logout delivery failed: {:?}", err);
+        }
+
+        if status != DELIVERY_STATUS_PENDING {
+            return;
+        }
+        tokio::time::sleep(delay).await;
+        delay *= 2;
+    }
+}
+
+// End a login session and send a logout token to every application that
+// received tokens in it. Deliveries run in the background; the handles are
+// returned for callers that have to wait for them.
+pub async fn end_login_session(
+    conn: &DatabaseConnection,
+    oidc_keys: &OidcKeys,
+    sid: &str,
+) -> Result<Vec<JoinHandle<()>>, AppError> {
+    let session_model = LoginSessionModel::new(conn);
+    let session = match session_model.end_session(sid).await? {
+        Some(session) => session,
+        None => return Ok(vec![]),
+    };
+
+    let delivery_model = BackchannelLogoutDeliveryModel::new(conn);
+    let mut deliveries = Vec::new();
+
+    for app in session_model.find_participants(sid).await? {
+        let Some(logout_uri) = app.backchannel_logout_uri.clone() else {
+            continue;
+        };
+
+        let logout_token = logout_token(oidc_keys, &app, &session)?;
+        let delivery = delivery_model
+            .insert_delivery(CreateDeliveryParams {
+                sid: session.sid.clone(),
+                application_id: app.id,
+                logout_uri,
+            })
+            .await?;
+
+        deliveries.push(tokio::spawn(deliver(conn.clone(), delivery, logout_token)));
+    }
+
+    Ok(deliveries)
+}
+
+// Sign a user out everywhere: drop every live session from redis and notify
+// the relying parties, waiting until all deliveries have finished.
+pub async fn revoke_user_sessions(
+    conn: &DatabaseConnection,
+    redis_client: &redis::Client,
+    oidc_keys: &OidcKeys,
+    user_id: i32,
+) -> Result<usize, AppError> {
+    let sessions = LoginSessionModel::new(conn)
+        .find_active_sessions_by_user_id(&user_id)
+        .await?;
+
+    let mut deliveries = Vec::new();
+    for session in &sessions {
+        delete_key(redis_client, &session.session_id).await?;
+        deliveries.extend(end_login_session(conn, oidc_keys, &session.sid).await?);
+    }
+
+    for delivery in deliveries {
+        delivery.await.map_err(anyhow::Error::from)?;
+    }
+
+    Ok(sessions.len())
+}
@@ -0,0 +1,46 @@
+use entity::{image, user};
+use serde::{Deserialize, Serialize};
+
+use super::scope::Scopes;
+use crate::constants::ENVS;
+
+// Standard claims about the end user, released according to the granted scopes
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct UserClaims {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub preferred_username: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nickname: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub picture: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub updated_at: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub email_verified: Option<bool>,
+}
+
+impl UserClaims {
+    pub fn new(user: &user::Model, face: Option<&image::Model>, scopes: &Scopes) -> Self {
+        let mut claims = UserClaims::default();
+
+        if scopes.contains("profile") {
+            claims.name = Some(user.nickname.clone());
+            claims.preferred_username = Some(user.username.clone());
+            claims.nickname = Some(user.nickname.clone());
+            claims.picture = face.map(|f| format!("{}{}", ENVS.cdn_base_url, f.path));
+            claims.updated_at = Some(user.updated_at.and_utc().timestamp());
+        }
+
+        if scopes.contains("email") {
+            // Addresses are never confirmed at registration
+            claims.email_verified = user.email.as_ref().map(|_| false);
+            claims.email = user.email.clone();
+        }
+
+        claims
+    }
+}
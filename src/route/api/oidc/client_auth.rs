@@ -0,0 +1,374 @@
+use std::collections::HashSet;
+
+use axum::http::{header::AUTHORIZATION, HeaderMap};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use jsonwebtoken::{
+    decode, decode_header,
+    jwk::{Jwk, JwkSet},
+    Algorithm, DecodingKey, Validation,
+};
+use openssl::memcmp;
+use redis::AsyncCommands;
+use sea_orm::{ConnectionTrait, EntityTrait, ModelTrait};
+use serde::Deserialize;
+
+use entity::{application, application_secret};
+
+use crate::{
+    constants::{
+        AUTH_METHOD_CLIENT_SECRET_BASIC, AUTH_METHOD_CLIENT_SECRET_JWT,
+        AUTH_METHOD_CLIENT_SECRET_POST, AUTH_METHOD_NONE, AUTH_METHOD_PRIVATE_KEY_JWT,
+        CLIENT_ASSERTION_REDIS_KEY, CLIENT_JWKS_REDIS_KEY, PARSED_FRONTEND_URL,
+    },
+    error::{AppError, ServiceError},
+    http_client::fetch,
+    storage::redis::set_once,
+};
+
+pub const TOKEN_ENDPOINT_AUTH_METHODS: [&str; 5] = [
+    AUTH_METHOD_CLIENT_SECRET_POST,
+    AUTH_METHOD_CLIENT_SECRET_BASIC,
+    AUTH_METHOD_CLIENT_SECRET_JWT,
+    AUTH_METHOD_PRIVATE_KEY_JWT,
+    AUTH_METHOD_NONE,
+];
+
+pub const CLIENT_ASSERTION_SIGNING_ALGORITHMS: [Algorithm; 9] = [
+    Algorithm::HS256,
+    Algorithm::HS384,
+    Algorithm::HS512,
+    Algorithm::RS256,
+    Algorithm::RS384,
+    Algorithm::RS512,
+    Algorithm::PS256,
+    Algorithm::ES256,
+    Algorithm::EdDSA,
+];
+
+const CLIENT_ASSERTION_TYPE_JWT_BEARER: &str =
+    "urn:ietf:params:oauth:client-assertion-type:jwt-bearer";
+const JWKS_CACHE_TTL: usize = 300;
+
+// Client credentials as they may appear in a form body. They are flattened
+// into the request of every endpoint that authenticates clients.
+#[derive(Debug, Default, Deserialize)]
+pub struct ClientAuthParams {
+    pub client_id: Option<String>,
+    client_secret: Option<String>,
+    client_assertion_type: Option<String>,
+    client_assertion: Option<String>,
+}
+
+enum Credential {
+    Secret(String),
+    Assertion { token: String, alg: Algorithm },
+    Public,
+}
+
+struct PresentedCredential {
+    client_id: String,
+    method: &'static str,
+    credential: Credential,
+}
+
+#[derive(Debug, Deserialize)]
+struct AssertionClaims {
+    sub: String,
+    exp: i64,
+    jti: String,
+}
+
+fn invalid_client() -> AppError {
+    AppError::ServiceError(ServiceError::InvalidClient)
+}
+
+fn secret_matches(expected: &str, provided: &str) -> bool {
+    expected.len() == provided.len() && memcmp::eq(expected.as_bytes(), provided.as_bytes())
+}
+
+// The id and secret in a Basic header are form-urlencoded before being joined (RFC 6749 2.3.1).
+fn form_urlencoded_decode(value: &str) -> String {
+    let escaped = value.replace('=', "%3D").replace('&', "%26");
+    url::form_urlencoded::parse(escaped.as_bytes())
+        .next()
+        .map(|(decoded, _)| decoded.into_owned())
+        .unwrap_or_default()
+}
+
+fn basic_credentials(headers: &HeaderMap) -> Result<Option<(String, String)>, AppError> {
+    let Some(authorization) = headers.get(AUTHORIZATION) else {
+        return Ok(None);
+    };
+
+    let encoded = authorization
+        .to_str()
+        .ok()
+        .and_then(|value| value.split_once(' '))
+        .filter(|(scheme, _)| scheme.eq_ignore_ascii_case("basic"))
+        .map(|(_, encoded)| encoded.trim())
+        .ok_or_else(invalid_client)?;
+    let decoded = STANDARD
+        .decode(encoded)
+        .ok()
+        .and_then(|decoded| String::from_utf8(decoded).ok())
+        .ok_or_else(invalid_client)?;
+    let (client_id, client_secret) = decoded.split_once(':').ok_or_else(invalid_client)?;
+
+    Ok(Some((
+        form_urlencoded_decode(client_id),
+        form_urlencoded_decode(client_secret),
+    )))
+}
+
+// The client id of an assertion is its subject, read before the signature is
+// checked only to find the key to check it with.
+fn unverified_assertion_subject(token: &str) -> Option<String> {
+    let mut validation = Validation::default();
+    validation.insecure_disable_signature_validation();
+    validation.validate_exp = false;
+    validation.required_spec_claims = HashSet::new();
+
+    decode::<AssertionClaims>(token, &DecodingKey::from_secret(&[]), &validation)
+        .ok()
+        .map(|token_data| token_data.claims.sub)
+}
+
+// Work out which method the client used. Presenting more than one is rejected.
+fn presented_credential(
+    headers: &HeaderMap,
+    params: &ClientAuthParams,
+) -> Result<PresentedCredential, AppError> {
+    let basic = basic_credentials(headers)?;
+
+    if let Some((client_id, client_secret)) = basic {
+        if params.client_secret.is_some()
+            || params.client_assertion.is_some()
+            || params
+                .client_id
+                .as_ref()
+                .map_or(false, |form_client_id| *form_client_id != client_id)
+        {
+            return Err(invalid_client());
+        }
+
+        return Ok(PresentedCredential {
+            client_id,
+            method: AUTH_METHOD_CLIENT_SECRET_BASIC,
+            credential: Credential::Secret(client_secret),
+        });
+    }
+
+    if let Some(token) = &params.client_assertion {
+        if params.client_secret.is_some()
+            || params.client_assertion_type.as_deref() != Some(CLIENT_ASSERTION_TYPE_JWT_BEARER)
+        {
+            return Err(invalid_client());
+        }
+
+        let alg = decode_header(token).map_err(|_| invalid_client())?.alg;
+        if !CLIENT_ASSERTION_SIGNING_ALGORITHMS.contains(&alg) {
+            return Err(invalid_client());
+        }
+
+        let client_id = match &params.client_id {
+            Some(client_id) => client_id.clone(),
+            None => unverified_assertion_subject(token).ok_or_else(invalid_client)?,
+        };
+        let method = match alg {
+            Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512 => AUTH_METHOD_CLIENT_SECRET_JWT,
+            _ => AUTH_METHOD_PRIVATE_KEY_JWT,
+        };
+
+        return Ok(PresentedCredential {
+            client_id,
+            method,
+            credential: Credential::Assertion {
+                token: token.clone(),
+                alg,
+            },
+        });
+    }
+
+    let client_id = params.client_id.clone().ok_or_else(invalid_client)?;
+
+    Ok(match &params.client_secret {
+        Some(client_secret) => PresentedCredential {
+            client_id,
+            method: AUTH_METHOD_CLIENT_SECRET_POST,
+            credential: Credential::Secret(client_secret.clone()),
+        },
+        None => PresentedCredential {
+            client_id,
+            method: AUTH_METHOD_NONE,
+            credential: Credential::Public,
+        },
+    })
+}
+
+async fn fetch_jwks(redis_client: &redis::Client, jwks_uri: &str) -> Result<JwkSet, AppError> {
+    let mut conn = redis_client.get_async_connection().await?;
+    let cache_key = format!("{}:{}", CLIENT_JWKS_REDIS_KEY, jwks_uri);
+
+    let cached: Option<String> = conn.get(&cache_key).await?;
+    if let Some(jwks) = cached.and_then(|cached| serde_json::from_str(&cached).ok()) {
+        return Ok(jwks);
+    }
+
+    let body = fetch(jwks_uri).await.map_err(|err| {
+        tracing::warn!("Fetching client jwks from {} failed: {}", jwks_uri, err);
+        invalid_client()
+    })?;
+    let jwks: JwkSet = serde_json::from_slice(&body).map_err(|_| invalid_client())?;
+
+    let _: () = conn
+        .set_ex(
+            &cache_key,
+            String::from_utf8_lossy(&body).as_ref(),
+            JWKS_CACHE_TTL,
+        )
+        .await?;
+
+    Ok(jwks)
+}
+
+// Keys registered inline take precedence over the ones published at `jwks_uri`.
+pub async fn client_public_keys(
+    redis_client: &redis::Client,
+    app: &application::Model,
+) -> Result<Vec<Jwk>, AppError> {
+    if let Some(jwks) = &app.jwks {
+        let jwks: JwkSet = serde_json::from_str(jwks).map_err(|_| invalid_client())?;
+        return Ok(jwks.keys);
+    }
+
+    match &app.jwks_uri {
+        Some(jwks_uri) => Ok(fetch_jwks(redis_client, jwks_uri).await?.keys),
+        None => Err(invalid_client()),
+    }
+}
+
+fn assertion_validation(client_id: &str, alg: Algorithm) -> Validation {
+    let issuer = PARSED_FRONTEND_URL.to_string();
+    let mut validation = Validation::new(alg);
+    validation.set_audience(&[issuer.clone(), format!("{}api/oidc/token", issuer)]);
+    validation.set_issuer(&[client_id]);
+    validation.set_required_spec_claims(&["exp", "iss", "sub", "aud"]);
+    validation.sub = Some(client_id.to_string());
+
+    validation
+}
+
+async fn verify_assertion<C: ConnectionTrait>(
+    db: &C,
+    redis_client: &redis::Client,
+    app: &application::Model,
+    token: &str,
+    alg: Algorithm,
+) -> Result<(), AppError> {
+    let validation = assertion_validation(&app.id, alg);
+
+    let decoding_keys = if app.token_endpoint_auth_method == AUTH_METHOD_CLIENT_SECRET_JWT {
+        app.find_related(application_secret::Entity)
+            .all(db)
+            .await?
+            .into_iter()
+            .map(|secret| DecodingKey::from_secret(secret.secret.as_bytes()))
+            .collect()
+    } else {
+        let kid = decode_header(token).ok().and_then(|header| header.kid);
+        client_public_keys(redis_client, app)
+            .await?
+            .iter()
+            .filter(|jwk| kid.is_none() || jwk.common.key_id == kid)
+            .filter_map(|jwk| DecodingKey::from_jwk(jwk).ok())
+            .collect::<Vec<_>>()
+    };
+
+    let claims = decoding_keys
+        .iter()
+        .find_map(|key| decode::<AssertionClaims>(token, key, &validation).ok())
+        .ok_or_else(invalid_client)?
+        .claims;
+
+    // Each assertion may only be used once while it is valid.
+    let ttl = claims.exp - chrono::Utc::now().timestamp();
+    let key = format!("{}:{}:{}", CLIENT_ASSERTION_REDIS_KEY, app.id, claims.jti);
+    if !set_once(redis_client, &key, ttl.max(1) as usize).await? {
+        return Err(invalid_client());
+    }
+
+    Ok(())
+}
+
+// Resolve the calling application and check its credential with the method it
+// registered. Secrets are checked against every secret the application
+// currently holds, so they can be rotated without downtime.
+pub async fn authenticate_client<C: ConnectionTrait>(
+    db: &C,
+    redis_client: &redis::Client,
+    headers: &HeaderMap,
+    params: &ClientAuthParams,
+) -> Result<application::Model, AppError> {
+    let presented = presented_credential(headers, params)?;
+
+    let app = application::Entity::find_by_id(presented.client_id)
+        .one(db)
+        .await?
+        .ok_or_else(invalid_client)?;
+
+    if app.token_endpoint_auth_method != presented.method {
+        return Err(invalid_client());
+    }
+
+    match presented.credential {
+        Credential::Secret(client_secret) => {
+            let secrets = app.find_related(application_secret::Entity).all(db).await?;
+
+            if !secrets
+                .iter()
+                .any(|secret| secret_matches(&secret.secret, &client_secret))
+            {
+                return Err(invalid_client());
+            }
+        }
+        Credential::Assertion { token, alg } => {
+            verify_assertion(db, redis_client, &app, &token, alg).await?
+        }
+        Credential::Public => {}
+    }
+
+    Ok(app)
+}
+
+// Public clients hold no credentials and are identified by their id alone.
+pub fn is_public_client(app: &application::Model) -> bool {
+    app.token_endpoint_auth_method == AUTH_METHOD_NONE
+}
+
+// `application.grant_types` holds the JSON list of grants the client may use.
+pub fn grant_type_allowed(app: &application::Model, grant_type: &str) -> bool {
+    serde_json::from_str::<HashSet<String>>(&app.grant_types)
+        .map_or(false, |grant_types| grant_types.contains(grant_type))
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::{header::AUTHORIZATION, HeaderMap};
+
+    use super::basic_credentials;
+
+    #[test]
+    fn basic_credentials_are_form_urlencoded() {
+        let mut headers = HeaderMap::new();
+        // "my%20client:s%3Acret+x" base64-encoded
+        headers.insert(
+            AUTHORIZATION,
+            "Basic bXklMjBjbGllbnQ6cyUzQWNyZXQreA==".parse().unwrap(),
+        );
+
+        assert_eq!(
+            basic_credentials(&headers).unwrap(),
+            Some(("my client".to_string(), "s:cret x".to_string()))
+        );
+    }
+}
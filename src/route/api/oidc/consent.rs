@@ -0,0 +1,168 @@
+use async_redis_session::RedisSessionStore;
+use async_session::{Session, SessionStore};
+use axum::{
+    extract::{Extension, Path},
+    Json,
+};
+use sea_orm::DatabaseConnection;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    constants::{CONSENT_REQUEST_REDIS_KEY, ENVS},
+    error::{AppError, ServiceError},
+    extractor::user_id_from_session::UserIdFromSession,
+    model::{application::ApplicationModel, application_access_grant::ApplicationAccessGrantModel},
+    response::OkResponse,
+};
+
+use super::scope::{scopes_from_json, scopes_to_json, Scopes};
+
+const CONSENT_EXPIRES_TIME: u64 = 10 * 60;
+
+#[derive(Serialize, Deserialize)]
+pub struct ConsentRequest {
+    pub user_id: i32,
+    pub client_id: String,
+    pub scopes: Scopes,
+    // The authorize request to resume once the user decides
+    pub return_to: String,
+}
+
+impl ConsentRequest {
+    pub async fn store(self, store: &RedisSessionStore) -> async_session::Result<String> {
+        let mut session = Session::new();
+        session.insert(CONSENT_REQUEST_REDIS_KEY, self)?;
+        session.expire_in(std::time::Duration::from_secs(CONSENT_EXPIRES_TIME));
+
+        Ok(store.store_session(session).await?.unwrap())
+    }
+}
+
+#[derive(Deserialize)]
+pub struct ConsentUrlParams {
+    pub consent_id: String,
+}
+
+#[derive(Serialize)]
+struct ResponseApplication {
+    id: String,
+    name: String,
+    icon_url: Option<String>,
+    description: Option<String>,
+    homepage_url: String,
+}
+
+#[derive(Serialize)]
+pub struct GetSuccessResponse {
+    application: ResponseApplication,
+    scopes: Scopes,
+    granted_scopes: Scopes,
+}
+
+#[derive(Deserialize)]
+pub struct PostConsentParams {
+    approve: bool,
+}
+
+#[derive(Serialize)]
+pub struct PostSuccessResponse {
+    redirect_to: String,
+}
+
+async fn load_consent_request(
+    store: &RedisSessionStore,
+    consent_id: String,
+    user_id: i32,
+) -> Result<(Session, ConsentRequest), AppError> {
+    let session = store
+        .load_session(consent_id)
+        .await?
+        .ok_or(ServiceError::NotFound)?;
+
+    let consent_request = session
+        .get::<ConsentRequest>(CONSENT_REQUEST_REDIS_KEY)
+        .ok_or(ServiceError::NotFound)?;
+
+    if consent_request.user_id != user_id {
+        return Err(ServiceError::PermissionDenied.into());
+    }
+
+    Ok((session, consent_request))
+}
+
+pub async fn get_handler(
+    Extension(conn): Extension<DatabaseConnection>,
+    Extension(store): Extension<RedisSessionStore>,
+    Path(url_params): Path<ConsentUrlParams>,
+    user_id_from_session: UserIdFromSession,
+) -> Result<OkResponse<GetSuccessResponse>, AppError> {
+    let (_, consent_request) =
+        load_consent_request(&store, url_params.consent_id, user_id_from_session.user_id).await?;
+
+    let (application, icon) = ApplicationModel::new(&conn)
+        .find_one_application_by_id(&consent_request.client_id)
+        .await?
+        .ok_or(ServiceError::NotFound)?;
+
+    let granted_scopes = ApplicationAccessGrantModel::new(&conn)
+        .find_one_grant(&user_id_from_session.user_id, &application.id)
+        .await?
+        .map(|grant| scopes_from_json(&grant.scopes))
+        .unwrap_or_default();
+
+    Ok(OkResponse::new(GetSuccessResponse {
+        application: ResponseApplication {
+            id: application.id,
+            name: application.name,
+            icon_url: icon.map(|f| format!("{}{}", ENVS.cdn_base_url, f.path)),
+            description: application.description,
+            homepage_url: application.homepage_url,
+        },
+        scopes: consent_request.scopes,
+        granted_scopes,
+    }))
+}
+
+pub async fn post_handler(
+    Extension(conn): Extension<DatabaseConnection>,
+    Extension(store): Extension<RedisSessionStore>,
+    Path(url_params): Path<ConsentUrlParams>,
+    user_id_from_session: UserIdFromSession,
+    Json(params): Json<PostConsentParams>,
+) -> Result<OkResponse<PostSuccessResponse>, AppError> {
+    let user_id = user_id_from_session.user_id;
+    let (session, consent_request) =
+        load_consent_request(&store, url_params.consent_id, user_id).await?;
+
+    store.destroy_session(session).await?;
+
+    // The authorize endpoint reports the refusal to the client in the
+    // response mode it asked for
+    if !params.approve {
+        return Ok(OkResponse::new(PostSuccessResponse {
+            redirect_to: format!("{}&consent_denied=1", consent_request.return_to),
+        }));
+    }
+
+    let grant_model = ApplicationAccessGrantModel::new(&conn);
+
+    // Approving more scopes later widens the grant rather than replacing it
+    let mut scopes = grant_model
+        .find_one_grant(&user_id, &consent_request.client_id)
+        .await?
+        .map(|grant| scopes_from_json(&grant.scopes))
+        .unwrap_or_default();
+    scopes.extend(consent_request.scopes);
+
+    grant_model
+        .save_grant(
+            &user_id,
+            &consent_request.client_id,
+            scopes_to_json(&scopes),
+        )
+        .await?;
+
+    Ok(OkResponse::new(PostSuccessResponse {
+        redirect_to: consent_request.return_to,
+    }))
+}
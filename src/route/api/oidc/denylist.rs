@@ -0,0 +1,57 @@
+use axum::{
+    extract::{Extension, Form},
+    http::HeaderMap,
+    response::{IntoResponse, Json},
+};
+use sea_orm::DatabaseConnection;
+use serde::{Deserialize, Serialize};
+
+use super::client_auth::{authenticate_client, is_public_client, ClientAuthParams};
+use crate::{
+    error::{OAuthError, ServiceError},
+    model::token_denylist::TokenDenylistModel,
+};
+
+#[derive(Debug, Deserialize)]
+pub struct DenylistRequest {
+    #[serde(flatten)]
+    client: ClientAuthParams,
+}
+
+#[derive(Debug, Serialize)]
+struct DenylistEntry {
+    jti: String,
+    exp: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DenylistResponse {
+    denylist: Vec<DenylistEntry>,
+}
+
+// Resource servers validating JWT access tokens offline poll this list to
+// learn about revoked tokens that have not expired yet.
+pub async fn handler(
+    Extension(conn): Extension<DatabaseConnection>,
+    Extension(redis_client): Extension<redis::Client>,
+    headers: HeaderMap,
+    Form(form): Form<DenylistRequest>,
+) -> Result<impl IntoResponse, OAuthError> {
+    let app = authenticate_client(&conn, &redis_client, &headers, &form.client).await?;
+
+    if is_public_client(&app) {
+        return Err(ServiceError::UnauthorizedClient.into());
+    }
+
+    let entries = TokenDenylistModel::new(&conn).find_active_entries().await?;
+
+    Ok(Json(DenylistResponse {
+        denylist: entries
+            .into_iter()
+            .map(|entry| DenylistEntry {
+                jti: entry.jti,
+                exp: entry.expires_at.and_utc().timestamp(),
+            })
+            .collect(),
+    }))
+}
@@ -0,0 +1,397 @@
+use axum::{
+    extract::{Extension, Form, Query},
+    http::HeaderMap,
+    response::Json,
+};
+use openssl::rand::rand_bytes;
+use redis::AsyncCommands;
+use sea_orm::DatabaseConnection;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    client_auth::{authenticate_client, grant_type_allowed, ClientAuthParams},
+    scope::{parse_scope, scopes_from_json, scopes_to_json, Scopes},
+};
+use crate::{
+    constants::{DEVICE_CODE_REDIS_KEY, DEVICE_USER_CODE_REDIS_KEY, ENVS, PARSED_FRONTEND_URL},
+    error::{AppError, OAuthError, ServiceError},
+    extractor::user_id_from_session::UserIdFromSession,
+    model::{application::ApplicationModel, application_access_grant::ApplicationAccessGrantModel},
+    response::OkResponse,
+    storage::redis::set_once,
+};
+
+pub const DEVICE_CODE_GRANT_TYPE: &str = "urn:ietf:params:oauth:grant-type:device_code";
+
+const DEVICE_CODE_EXPIRES_IN: i64 = 10 * 60;
+// Seconds a client has to wait between polls. Clients add 5 seconds on every `slow_down`.
+const DEVICE_CODE_INTERVAL: i64 = 5;
+// Consonants only, so codes are easy to type and never spell words (RFC 8628 section 6.1)
+const USER_CODE_ALPHABET: &[u8] = b"BCDFGHJKLMNPQRSTVWXZ";
+const USER_CODE_LENGTH: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeviceAuthorizationStatus {
+    Pending,
+    Approved,
+    Denied,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DeviceAuthorization {
+    pub client_id: String,
+    pub scopes: Scopes,
+    pub user_code: String,
+    pub status: DeviceAuthorizationStatus,
+    // Set once a signed-in user approves the request
+    pub user_id: Option<i32>,
+    pub auth_time: Option<i64>,
+    pub sid: Option<String>,
+    pub expires_at: i64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DeviceAuthorizationRequest {
+    scope: Option<String>,
+    #[serde(flatten)]
+    client: ClientAuthParams,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DeviceAuthorizationResponse {
+    device_code: String,
+    user_code: String,
+    verification_uri: String,
+    verification_uri_complete: String,
+    expires_in: i64,
+    interval: i64,
+}
+
+#[derive(Deserialize)]
+pub struct DeviceVerificationQuery {
+    user_code: String,
+}
+
+#[derive(Serialize)]
+struct ResponseApplication {
+    id: String,
+    name: String,
+    icon_url: Option<String>,
+    description: Option<String>,
+    homepage_url: String,
+}
+
+#[derive(Serialize)]
+pub struct GetSuccessResponse {
+    application: ResponseApplication,
+    scopes: Scopes,
+    granted_scopes: Scopes,
+}
+
+#[derive(Deserialize)]
+pub struct PostDeviceVerificationParams {
+    user_code: String,
+    approve: bool,
+}
+
+#[derive(Serialize)]
+pub struct PostSuccessResponse {}
+
+fn device_code_key(device_code: &str) -> String {
+    format!("{}:{}", DEVICE_CODE_REDIS_KEY, device_code)
+}
+
+fn poll_key(device_code: &str) -> String {
+    format!("{}:poll:{}", DEVICE_CODE_REDIS_KEY, device_code)
+}
+
+fn user_code_key(user_code: &str) -> String {
+    format!("{}:{}", DEVICE_USER_CODE_REDIS_KEY, user_code)
+}
+
+// Rejection sampling keeps every letter equally likely.
+fn generate_user_code() -> Result<String, AppError> {
+    let limit = u8::MAX - u8::MAX % USER_CODE_ALPHABET.len() as u8;
+    let mut code = String::with_capacity(USER_CODE_LENGTH);
+    let mut byte = [0u8; 1];
+
+    while code.len() < USER_CODE_LENGTH {
+        rand_bytes(&mut byte)?;
+        if byte[0] < limit {
+            code.push(
+                USER_CODE_ALPHABET[(byte[0] % USER_CODE_ALPHABET.len() as u8) as usize] as char,
+            );
+        }
+    }
+
+    Ok(code)
+}
+
+fn format_user_code(user_code: &str) -> String {
+    format!("{}-{}", &user_code[..4], &user_code[4..])
+}
+
+// Users may type the code in lower case, with or without the dash.
+fn normalize_user_code(user_code: &str) -> String {
+    user_code
+        .chars()
+        .filter(|char| char.is_ascii_alphanumeric())
+        .map(|char| char.to_ascii_uppercase())
+        .collect()
+}
+
+async fn save_device_authorization(
+    conn: &mut redis::aio::Connection,
+    device_code: &str,
+    authorization: &DeviceAuthorization,
+) -> Result<(), AppError> {
+    let ttl = authorization.expires_at - chrono::Utc::now().timestamp();
+    if ttl <= 0 {
+        return Err(AppError::ServiceError(ServiceError::ExpiredToken));
+    }
+
+    let _: () = conn
+        .set_ex(
+            device_code_key(device_code),
+            serde_json::to_string(authorization).unwrap(),
+            ttl as usize,
+        )
+        .await?;
+
+    Ok(())
+}
+
+async fn load_device_authorization(
+    conn: &mut redis::aio::Connection,
+    device_code: &str,
+) -> Result<Option<DeviceAuthorization>, AppError> {
+    let value: Option<String> = conn.get(device_code_key(device_code)).await?;
+
+    Ok(value.and_then(|value| serde_json::from_str(&value).ok()))
+}
+
+async fn find_by_user_code(
+    conn: &mut redis::aio::Connection,
+    user_code: &str,
+) -> Result<(String, DeviceAuthorization), AppError> {
+    let device_code: String = conn
+        .get::<_, Option<String>>(user_code_key(&normalize_user_code(user_code)))
+        .await?
+        .ok_or(ServiceError::NotFound)?;
+
+    let authorization = load_device_authorization(conn, &device_code)
+        .await?
+        .filter(|authorization| authorization.status == DeviceAuthorizationStatus::Pending)
+        .ok_or(ServiceError::NotFound)?;
+
+    Ok((device_code, authorization))
+}
+
+// Apply the polling rules of RFC 8628 section 3.5, returning the authorization
+// once it is approved. Only the first poll that sees the approval gets it.
+pub async fn poll_device_authorization(
+    redis_client: &redis::Client,
+    client_id: &str,
+    device_code: &str,
+) -> Result<DeviceAuthorization, AppError> {
+    let mut conn = redis_client.get_async_connection().await?;
+    let authorization = load_device_authorization(&mut conn, device_code)
+        .await?
+        .ok_or(AppError::ServiceError(ServiceError::ExpiredToken))?;
+
+    if authorization.client_id != client_id {
+        return Err(AppError::ServiceError(ServiceError::InvalidGrant));
+    }
+
+    match authorization.status {
+        DeviceAuthorizationStatus::Approved => {
+            let deleted: i32 = conn.del(device_code_key(device_code)).await?;
+            if deleted == 0 {
+                return Err(AppError::ServiceError(ServiceError::InvalidGrant));
+            }
+
+            Ok(authorization)
+        }
+        DeviceAuthorizationStatus::Denied => {
+            let _: () = conn.del(device_code_key(device_code)).await?;
+
+            Err(AppError::ServiceError(ServiceError::AccessDenied))
+        }
+        // The poll marker lives apart from the authorization so that polling
+        // never overwrites an approval made in the meantime
+        DeviceAuthorizationStatus::Pending => {
+            match set_once(
+                redis_client,
+                &poll_key(device_code),
+                DEVICE_CODE_INTERVAL as usize,
+            )
+            .await?
+            {
+                true => Err(AppError::ServiceError(ServiceError::AuthorizationPending)),
+                false => Err(AppError::ServiceError(ServiceError::SlowDown)),
+            }
+        }
+    }
+}
+
+pub async fn authorization_handler(
+    Extension(conn): Extension<DatabaseConnection>,
+    Extension(redis_client): Extension<redis::Client>,
+    headers: HeaderMap,
+    Form(form): Form<DeviceAuthorizationRequest>,
+) -> Result<Json<DeviceAuthorizationResponse>, OAuthError> {
+    let app = authenticate_client(&conn, &redis_client, &headers, &form.client).await?;
+    if !grant_type_allowed(&app, DEVICE_CODE_GRANT_TYPE) {
+        return Err(ServiceError::UnauthorizedClient.into());
+    }
+
+    let device_code = uuid::Uuid::new_v4().to_string();
+    let user_code = generate_user_code()?;
+    let authorization = DeviceAuthorization {
+        client_id: app.id,
+        scopes: form.scope.as_deref().map(parse_scope).unwrap_or_default(),
+        user_code: user_code.clone(),
+        status: DeviceAuthorizationStatus::Pending,
+        user_id: None,
+        auth_time: None,
+        sid: None,
+        expires_at: chrono::Utc::now().timestamp() + DEVICE_CODE_EXPIRES_IN,
+    };
+
+    let mut redis_conn = redis_client.get_async_connection().await?;
+    save_device_authorization(&mut redis_conn, &device_code, &authorization).await?;
+    let _: () = redis_conn
+        .set_ex(
+            user_code_key(&user_code),
+            &device_code,
+            DEVICE_CODE_EXPIRES_IN as usize,
+        )
+        .await?;
+
+    let verification_uri = PARSED_FRONTEND_URL
+        .join("device")
+        .map_err(|err| AppError::UnexpectedError(err.into()))?;
+    let mut verification_uri_complete = verification_uri.clone();
+    verification_uri_complete
+        .query_pairs_mut()
+        .append_pair("user_code", &format_user_code(&user_code));
+
+    Ok(Json(DeviceAuthorizationResponse {
+        device_code,
+        user_code: format_user_code(&user_code),
+        verification_uri: verification_uri.to_string(),
+        verification_uri_complete: verification_uri_complete.to_string(),
+        expires_in: DEVICE_CODE_EXPIRES_IN,
+        interval: DEVICE_CODE_INTERVAL,
+    }))
+}
+
+// Shows the signed-in user what the device is asking for.
+pub async fn get_handler(
+    Extension(conn): Extension<DatabaseConnection>,
+    Extension(redis_client): Extension<redis::Client>,
+    user_id_from_session: UserIdFromSession,
+    Query(query): Query<DeviceVerificationQuery>,
+) -> Result<OkResponse<GetSuccessResponse>, AppError> {
+    let mut redis_conn = redis_client.get_async_connection().await?;
+    let (_, authorization) = find_by_user_code(&mut redis_conn, &query.user_code).await?;
+
+    let (application, icon) = ApplicationModel::new(&conn)
+        .find_one_application_by_id(&authorization.client_id)
+        .await?
+        .ok_or(ServiceError::NotFound)?;
+
+    let granted_scopes = ApplicationAccessGrantModel::new(&conn)
+        .find_one_grant(&user_id_from_session.user_id, &application.id)
+        .await?
+        .map(|grant| scopes_from_json(&grant.scopes))
+        .unwrap_or_default();
+
+    Ok(OkResponse::new(GetSuccessResponse {
+        application: ResponseApplication {
+            id: application.id,
+            name: application.name,
+            icon_url: icon.map(|f| format!("{}{}", ENVS.cdn_base_url, f.path)),
+            description: application.description,
+            homepage_url: application.homepage_url,
+        },
+        scopes: authorization.scopes,
+        granted_scopes,
+    }))
+}
+
+// Binds the signed-in user to the device request, or turns it down.
+pub async fn post_handler(
+    Extension(conn): Extension<DatabaseConnection>,
+    Extension(redis_client): Extension<redis::Client>,
+    user_id_from_session: UserIdFromSession,
+    Json(params): Json<PostDeviceVerificationParams>,
+) -> Result<OkResponse<PostSuccessResponse>, AppError> {
+    let mut redis_conn = redis_client.get_async_connection().await?;
+    let (device_code, mut authorization) =
+        find_by_user_code(&mut redis_conn, &params.user_code).await?;
+
+    // A user code can only be entered once
+    let deleted: i32 = redis_conn
+        .del(user_code_key(&authorization.user_code))
+        .await?;
+    if deleted == 0 {
+        return Err(ServiceError::NotFound.into());
+    }
+
+    if !params.approve {
+        authorization.status = DeviceAuthorizationStatus::Denied;
+        save_device_authorization(&mut redis_conn, &device_code, &authorization).await?;
+
+        return Ok(OkResponse::new(PostSuccessResponse {}));
+    }
+
+    let (application, _) = ApplicationModel::new(&conn)
+        .find_one_application_by_id(&authorization.client_id)
+        .await?
+        .ok_or(ServiceError::NotFound)?;
+
+    // Third-party apps keep the approval as a grant, like the consent page does
+    if application.first_party == 0 {
+        let user_id = user_id_from_session.user_id;
+        let grant_model = ApplicationAccessGrantModel::new(&conn);
+        let mut scopes = grant_model
+            .find_one_grant(&user_id, &application.id)
+            .await?
+            .map(|grant| scopes_from_json(&grant.scopes))
+            .unwrap_or_default();
+        scopes.extend(authorization.scopes.iter().cloned());
+
+        grant_model
+            .save_grant(&user_id, &application.id, scopes_to_json(&scopes))
+            .await?;
+    }
+
+    authorization.status = DeviceAuthorizationStatus::Approved;
+    authorization.user_id = Some(user_id_from_session.user_id);
+    authorization.auth_time = user_id_from_session.auth_time;
+    authorization.sid = user_id_from_session.sid;
+    save_device_authorization(&mut redis_conn, &device_code, &authorization).await?;
+
+    Ok(OkResponse::new(PostSuccessResponse {}))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{format_user_code, generate_user_code, normalize_user_code, USER_CODE_ALPHABET};
+
+    #[test]
+    fn user_codes_round_trip_through_normalization() {
+        let user_code = generate_user_code().unwrap();
+
+        assert_eq!(user_code.len(), 8);
+        assert!(user_code
+            .bytes()
+            .all(|byte| USER_CODE_ALPHABET.contains(&byte)));
+        assert_eq!(
+            normalize_user_code(&format_user_code(&user_code).to_lowercase()),
+            user_code
+        );
+    }
+}
@@ -0,0 +1,164 @@
+use axum::http::HeaderMap;
+use base64::prelude::*;
+use jsonwebtoken::{decode, decode_header, Algorithm, DecodingKey, Validation};
+use openssl::hash::{hash, MessageDigest};
+use serde::{Deserialize, Serialize};
+
+use super::keys::jwk_thumbprint;
+use crate::{
+    constants::{DPOP_PROOF_REDIS_KEY, PARSED_FRONTEND_URL},
+    error::{AppError, ServiceError},
+    storage::redis::set_once,
+    util::verify_dpop_nonce,
+};
+
+// Proofs are signed with the client's own key pair, so only asymmetric algorithms
+pub const DPOP_SIGNING_ALGORITHMS: [Algorithm; 6] = [
+    Algorithm::RS256,
+    Algorithm::RS384,
+    Algorithm::RS512,
+    Algorithm::PS256,
+    Algorithm::ES256,
+    Algorithm::EdDSA,
+];
+pub const DPOP_HEADER: &str = "DPoP";
+// The token type of DPoP-bound access tokens and the authorization scheme they are sent with
+pub const DPOP_TOKEN_TYPE: &str = "DPoP";
+const DPOP_PROOF_TYPE: &str = "dpop+jwt";
+// How old and how far in the future a proof's `iat` may be
+const DPOP_PROOF_MAX_AGE: i64 = 5 * 60;
+const DPOP_PROOF_CLOCK_SKEW: i64 = 60;
+
+// The `cnf` claim binding a token to the key of the client's proofs
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Confirmation {
+    pub jkt: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct DpopProofClaims {
+    jti: String,
+    htm: String,
+    htu: String,
+    iat: i64,
+    ath: Option<String>,
+    nonce: Option<String>,
+}
+
+// The request a proof has to be made for
+pub struct DpopRequest<'a> {
+    pub method: &'a str,
+    // Relative to the issuer, e.g. `api/oidc/token`
+    pub path: &'a str,
+    // Proofs sent to resource endpoints carry the hash of the access token
+    pub access_token: Option<&'a str>,
+}
+
+fn invalid_dpop_proof() -> AppError {
+    AppError::ServiceError(ServiceError::InvalidDpopProof)
+}
+
+// The proof names the URL without query and fragment
+fn htu_matches(htu: &str, expected: &url::Url) -> bool {
+    let Ok(mut htu) = url::Url::parse(htu) else {
+        return false;
+    };
+    htu.set_query(None);
+    htu.set_fragment(None);
+
+    htu == *expected
+}
+
+fn access_token_hash(access_token: &str) -> Option<String> {
+    hash(MessageDigest::sha256(), access_token.as_bytes())
+        .ok()
+        .map(|digest| BASE64_URL_SAFE_NO_PAD.encode(digest))
+}
+
+// Check the DPoP proof sent with the request, if any, and return the JWK
+// thumbprint of the key it was signed with.
+pub async fn verify_dpop_proof(
+    redis_client: &redis::Client,
+    headers: &HeaderMap,
+    request: DpopRequest<'_>,
+) -> Result<Option<String>, AppError> {
+    let mut proofs = headers.get_all(DPOP_HEADER).iter();
+    let proof = match (proofs.next(), proofs.next()) {
+        (None, _) => return Ok(None),
+        (Some(proof), None) => proof.to_str().map_err(|_| invalid_dpop_proof())?,
+        (Some(_), Some(_)) => return Err(invalid_dpop_proof()),
+    };
+
+    // The public key comes along in the header; only a public one is accepted
+    let header = decode_header(proof).map_err(|_| invalid_dpop_proof())?;
+    if header.typ.as_deref() != Some(DPOP_PROOF_TYPE)
+        || !DPOP_SIGNING_ALGORITHMS.contains(&header.alg)
+    {
+        return Err(invalid_dpop_proof());
+    }
+    let jwk = header.jwk.ok_or_else(invalid_dpop_proof)?;
+    let public_jwk = serde_json::to_value(&jwk).map_err(|_| invalid_dpop_proof())?;
+    if !matches!(public_jwk["kty"].as_str(), Some("EC" | "RSA" | "OKP")) {
+        return Err(invalid_dpop_proof());
+    }
+    let jkt = jwk_thumbprint(&public_jwk)?;
+
+    let mut validation = Validation::new(header.alg);
+    validation.set_required_spec_claims::<&str>(&[]);
+    validation.validate_exp = false;
+    validation.validate_aud = false;
+    let key = DecodingKey::from_jwk(&jwk).map_err(|_| invalid_dpop_proof())?;
+    let claims = decode::<DpopProofClaims>(proof, &key, &validation)
+        .map_err(|_| invalid_dpop_proof())?
+        .claims;
+
+    let expected_htu = PARSED_FRONTEND_URL
+        .join(request.path)
+        .map_err(|err| AppError::UnexpectedError(err.into()))?;
+    let age = chrono::Utc::now().timestamp() - claims.iat;
+    if claims.htm != request.method
+        || !htu_matches(&claims.htu, &expected_htu)
+        || !(-DPOP_PROOF_CLOCK_SKEW..=DPOP_PROOF_MAX_AGE).contains(&age)
+        || claims.ath != request.access_token.and_then(access_token_hash)
+    {
+        return Err(invalid_dpop_proof());
+    }
+
+    if !claims.nonce.as_deref().map_or(false, verify_dpop_nonce) {
+        return Err(AppError::ServiceError(ServiceError::UseDpopNonce));
+    }
+
+    // Each proof may only be used once while it is fresh
+    let key = format!("{}:{}:{}", DPOP_PROOF_REDIS_KEY, jkt, claims.jti);
+    if !set_once(
+        redis_client,
+        &key,
+        (DPOP_PROOF_MAX_AGE + DPOP_PROOF_CLOCK_SKEW) as usize,
+    )
+    .await?
+    {
+        return Err(invalid_dpop_proof());
+    }
+
+    Ok(Some(jkt))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::htu_matches;
+
+    #[test]
+    fn htu_ignores_query_and_fragment() {
+        let expected = url::Url::parse("https://sso.example.com/api/oidc/userinfo").unwrap();
+
+        assert!(htu_matches(
+            "https://sso.example.com/api/oidc/userinfo?schema=openid#top",
+            &expected
+        ));
+        assert!(!htu_matches(
+            "https://sso.example.com/api/oidc/token",
+            &expected
+        ));
+        assert!(!htu_matches("not a url", &expected));
+    }
+}
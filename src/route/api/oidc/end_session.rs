@@ -0,0 +1,158 @@
+use std::collections::HashSet;
+
+use async_redis_session::RedisSessionStore;
+use async_session::SessionStore;
+use axum::{
+    extract::{Extension, Form, Query},
+    http::{header, HeaderMap, HeaderValue},
+    response::{IntoResponse, Redirect, Response},
+};
+use axum_extra::{headers::Cookie, TypedHeader};
+use sea_orm::{DatabaseConnection, EntityTrait};
+use serde::Deserialize;
+
+use entity::application;
+
+use super::{
+    backchannel_logout::end_login_session,
+    frontchannel_logout::{frontchannel_logout_urls, logout_page},
+    id_token::verify_id_token_hint,
+    keys::OidcKeys,
+    register::is_valid_redirect_uri,
+};
+use crate::{
+    constants::{PARSED_FRONTEND_URL, SESSION_COOKIE_KEY},
+    error::{AppError, ServiceError},
+    extractor::user_id_from_session::UserIdFromSession,
+    route::api::auth::logout::logout_cookie,
+};
+
+#[derive(Debug, Deserialize)]
+pub struct EndSessionRequest {
+    id_token_hint: Option<String>,
+    client_id: Option<String>,
+    post_logout_redirect_uri: Option<String>,
+    state: Option<String>,
+}
+
+// The redirect target has to be registered by the client the logout is for.
+async fn post_logout_redirect(
+    conn: &DatabaseConnection,
+    client_id: Option<&str>,
+    post_logout_redirect_uri: &str,
+    state: Option<&str>,
+) -> Result<url::Url, AppError> {
+    let client_id = client_id.ok_or(AppError::ServiceError(ServiceError::InvalidClient))?;
+    let app = application::Entity::find_by_id(client_id.to_string())
+        .one(conn)
+        .await?
+        .ok_or(AppError::ServiceError(ServiceError::InvalidClient))?;
+
+    let registered: HashSet<String> = app
+        .post_logout_redirect_uris
+        .and_then(|uris| serde_json::from_str(&uris).ok())
+        .unwrap_or_default();
+    // Values saved before the scheme was checked must not reach the logout page
+    if !registered.contains(post_logout_redirect_uri)
+        || !is_valid_redirect_uri(post_logout_redirect_uri)
+    {
+        return Err(AppError::ServiceError(ServiceError::InvalidRedirectUri));
+    }
+
+    let mut redirect_url = url::Url::parse(post_logout_redirect_uri)
+        .map_err(|_| AppError::ServiceError(ServiceError::InvalidRedirectUri))?;
+    if let Some(state) = state {
+        redirect_url.query_pairs_mut().append_pair("state", state);
+    }
+
+    Ok(redirect_url)
+}
+
+async fn end_session(
+    conn: DatabaseConnection,
+    oidc_keys: OidcKeys,
+    store: RedisSessionStore,
+    cookie: Option<TypedHeader<Cookie>>,
+    request: EndSessionRequest,
+) -> Result<Response, AppError> {
+    let hint = request
+        .id_token_hint
+        .as_deref()
+        .map(|id_token_hint| verify_id_token_hint(&oidc_keys, id_token_hint))
+        .transpose()?;
+
+    let client_id = match (&request.client_id, &hint) {
+        (Some(client_id), Some(hint)) if *client_id != hint.aud => {
+            return Err(AppError::ServiceError(ServiceError::InvalidClient))
+        }
+        (Some(client_id), _) => Some(client_id.as_str()),
+        (None, hint) => hint.as_ref().map(|hint| hint.aud.as_str()),
+    };
+
+    let redirect_url = match &request.post_logout_redirect_uri {
+        Some(post_logout_redirect_uri) => {
+            post_logout_redirect(
+                &conn,
+                client_id,
+                post_logout_redirect_uri,
+                request.state.as_deref(),
+            )
+            .await?
+        }
+        None => PARSED_FRONTEND_URL.clone(),
+    };
+
+    let session = match cookie
+        .as_ref()
+        .and_then(|cookie| cookie.get(SESSION_COOKIE_KEY.as_str()))
+    {
+        Some(session_cookie) => store.load_session(session_cookie.to_string()).await?,
+        None => None,
+    };
+
+    // A hint for another user must not end the session of whoever is signed in now.
+    let mut headers = HeaderMap::new();
+    let mut logout_urls = Vec::new();
+    if let Some(session) = session {
+        let session_user = session.get::<UserIdFromSession>("user");
+        let session_user_id = session_user.as_ref().map(|user| user.user_id.to_string());
+        if hint.map_or(true, |hint| Some(hint.sub) == session_user_id) {
+            store.destroy_session(session).await?;
+            if let Some(sid) = session_user.and_then(|user| user.sid) {
+                end_login_session(&conn, &oidc_keys, &sid).await?;
+                logout_urls = frontchannel_logout_urls(&conn, &sid).await?;
+            }
+            headers.insert(
+                header::SET_COOKIE,
+                HeaderValue::from_str(&logout_cookie().to_string()).unwrap(),
+            );
+        }
+    }
+
+    // Front-channel logout needs the browser, so the redirect waits for a page of iframes
+    if !logout_urls.is_empty() {
+        return Ok((headers, logout_page(&logout_urls, &redirect_url)).into_response());
+    }
+
+    Ok((headers, Redirect::to(redirect_url.as_str())).into_response())
+}
+
+pub async fn get_handler(
+    Extension(conn): Extension<DatabaseConnection>,
+    Extension(oidc_keys): Extension<OidcKeys>,
+    Extension(store): Extension<RedisSessionStore>,
+    cookie: Option<TypedHeader<Cookie>>,
+    Query(request): Query<EndSessionRequest>,
+) -> Result<Response, AppError> {
+    end_session(conn, oidc_keys, store, cookie, request).await
+}
+
+pub async fn post_handler(
+    Extension(conn): Extension<DatabaseConnection>,
+    Extension(oidc_keys): Extension<OidcKeys>,
+    Extension(store): Extension<RedisSessionStore>,
+    cookie: Option<TypedHeader<Cookie>>,
+    Form(request): Form<EndSessionRequest>,
+) -> Result<Response, AppError> {
+    end_session(conn, oidc_keys, store, cookie, request).await
+}
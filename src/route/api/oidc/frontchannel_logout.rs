@@ -0,0 +1,105 @@
+use axum::response::Html;
+use sea_orm::DatabaseConnection;
+
+use crate::{
+    constants::PARSED_FRONTEND_URL, error::AppError, model::login_session::LoginSessionModel,
+};
+
+pub fn escape_html(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+fn frontchannel_logout_url(frontchannel_logout_uri: &str, sid: &str) -> Option<url::Url> {
+    let mut url = url::Url::parse(frontchannel_logout_uri).ok()?;
+    // Anything but http(s) in the iframe would run on the SSO origin
+    if url.scheme() != "https" && url.scheme() != "http" {
+        return None;
+    }
+    url.query_pairs_mut()
+        .append_pair("iss", PARSED_FRONTEND_URL.as_str())
+        .append_pair("sid", sid);
+
+    Some(url)
+}
+
+// The front-channel logout URIs of every application that took part in the
+// session, carrying `iss` and `sid` so they can tell which session ended.
+pub async fn frontchannel_logout_urls(
+    conn: &DatabaseConnection,
+    sid: &str,
+) -> Result<Vec<url::Url>, AppError> {
+    let participants = LoginSessionModel::new(conn).find_participants(sid).await?;
+
+    Ok(participants
+        .iter()
+        .filter_map(|app| app.frontchannel_logout_uri.as_deref())
+        .filter_map(|uri| frontchannel_logout_url(uri, sid))
+        .collect())
+}
+
+// Load every logout URI in a hidden iframe, then move on to `redirect_url`
+// once they have all loaded, or after a few seconds if one of them hangs.
+pub fn logout_page(logout_urls: &[url::Url], redirect_url: &url::Url) -> Html<String> {
+    let redirect = escape_html(redirect_url.as_str());
+    let iframes: String = logout_urls
+        .iter()
+        .map(|url| {
+            format!(
+                r#"<iframe src="{}" style="display:none"></iframe>"#,
+                escape_html(url.as_str())
+            )
+        })
+        .collect();
+    let redirect_script = escape_html(&serde_json::to_string(redirect_url.as_str()).unwrap());
+
+    Html(format!(
+        r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<meta http-equiv="refresh" content="5;url={redirect}">
+<title>Signing out</title>
+</head>
+<body onload="window.location.replace({redirect_script})">
+{iframes}
+<noscript><a href="{redirect}">Continue</a></noscript>
+</body>
+</html>
+"#
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{frontchannel_logout_url, logout_page};
+
+    #[test]
+    fn logout_page_escapes_urls() {
+        unsafe {
+            std::env::set_var("FRONT_END_URL", "https://sso.example.com");
+        }
+
+        let logout_url =
+            frontchannel_logout_url("https://app.example.com/logout?a=1", "sid-1").unwrap();
+        let redirect_url = url::Url::parse("https://app.example.com/bye?x=1&y='2'").unwrap();
+
+        assert_eq!(
+            logout_url.as_str(),
+            "https://app.example.com/logout?a=1&iss=https%3A%2F%2Fsso.example.com%2F&sid=sid-1"
+        );
+
+        let page = logout_page(&[logout_url], &redirect_url).0;
+        assert!(page.contains("logout?a=1&amp;iss="));
+        assert!(!page.contains("y='2'"));
+    }
+
+    #[test]
+    fn only_http_logout_uris_are_framed() {
+        assert!(frontchannel_logout_url("javascript:alert(1)//", "sid-1").is_none());
+    }
+}
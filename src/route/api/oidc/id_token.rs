@@ -0,0 +1,97 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use jsonwebtoken::Algorithm;
+use openssl::hash::{hash, MessageDigest};
+use serde::{Deserialize, Serialize};
+
+use super::{claims::UserClaims, keys::OidcKeys};
+use crate::{
+    constants::PARSED_FRONTEND_URL,
+    error::{AppError, ServiceError},
+};
+
+// Password login is the only authentication method, which is ISO/IEC 29115 level 1
+pub const PASSWORD_AMR: &str = "pwd";
+pub const PASSWORD_ACR: &str = "1";
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct IdTokenClaims {
+    pub iss: String,
+    pub sub: String,
+    pub aud: String,
+    pub exp: usize,
+    pub iat: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub auth_time: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub amr: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub acr: Option<String>,
+    pub azp: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub at_hash: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub c_hash: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sid: Option<String>,
+    #[serde(flatten)]
+    pub user_claims: UserClaims,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct IdTokenHintClaims {
+    pub iss: String,
+    pub sub: String,
+    pub aud: String,
+}
+
+// Expired ID tokens are still good hints, so only the signature and issuer are checked.
+pub fn verify_id_token_hint(
+    oidc_keys: &OidcKeys,
+    id_token_hint: &str,
+) -> Result<IdTokenHintClaims, AppError> {
+    oidc_keys
+        .verify::<IdTokenHintClaims>(id_token_hint)
+        .ok()
+        .filter(|claims| claims.iss == PARSED_FRONTEND_URL.to_string())
+        .ok_or(AppError::ServiceError(ServiceError::InvalidToken))
+}
+
+// The left-most half of the token hash, as used by `at_hash` and `c_hash`
+pub fn half_hash(algorithm: Algorithm, value: &str) -> Option<String> {
+    let digest = match algorithm {
+        Algorithm::HS256 | Algorithm::RS256 | Algorithm::PS256 | Algorithm::ES256 => {
+            MessageDigest::sha256()
+        }
+        Algorithm::HS384 | Algorithm::RS384 | Algorithm::PS384 | Algorithm::ES384 => {
+            MessageDigest::sha384()
+        }
+        Algorithm::HS512 | Algorithm::RS512 | Algorithm::PS512 | Algorithm::EdDSA => {
+            MessageDigest::sha512()
+        }
+    };
+    let hashed = hash(digest, value.as_bytes()).ok()?;
+
+    Some(URL_SAFE_NO_PAD.encode(&hashed[..hashed.len() / 2]))
+}
+
+#[cfg(test)]
+mod tests {
+    use jsonwebtoken::Algorithm;
+
+    use super::half_hash;
+
+    #[test]
+    fn at_hash_matches_oidc_example() {
+        // OpenID Connect Core 1.0 appendix A.3
+        assert_eq!(
+            half_hash(
+                Algorithm::RS256,
+                "jHkWEdUXMU1BwAsC4vtUsZwnNvTIxEl0z9K3vx5KF0Y"
+            )
+            .as_deref(),
+            Some("77QmUPtjPfzWtF2AnpK9RQ")
+        );
+    }
+}
@@ -0,0 +1,179 @@
+use axum::{
+    extract::{Extension, Form},
+    http::HeaderMap,
+    response::{IntoResponse, Json},
+};
+use sea_orm::{ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter};
+use serde::{Deserialize, Serialize};
+
+use entity::token;
+
+use super::{
+    access_token::access_token_lookup_key,
+    client_auth::{authenticate_client, is_public_client, ClientAuthParams},
+    dpop::{verify_dpop_proof, Confirmation, DpopRequest, DPOP_TOKEN_TYPE},
+    keys::OidcKeys,
+    scope::{scopes_from_json, to_scope_string},
+    token_exchange::Actor,
+};
+use crate::{
+    constants::ENVS,
+    error::{AppError, OAuthError, ServiceError},
+};
+
+const INTROSPECTION_ENDPOINT_PATH: &str = "api/oidc/introspect";
+
+#[derive(Debug, Deserialize)]
+pub struct IntrospectRequest {
+    token: String,
+    token_type_hint: Option<String>,
+    #[serde(flatten)]
+    client: ClientAuthParams,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct IntrospectResponse {
+    active: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    scope: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    client_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sub: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    exp: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    iat: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    token_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    aud: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    act: Option<Actor>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cnf: Option<Confirmation>,
+}
+
+impl IntrospectResponse {
+    fn inactive() -> Self {
+        Self::default()
+    }
+
+    fn active(token: token::Model, exp: chrono::NaiveDateTime, token_type: &str) -> Self {
+        // Exchanged tokens are aimed at the service they were exchanged for
+        let aud = token
+            .audience
+            .clone()
+            .unwrap_or_else(|| ENVS.access_token_audience.clone());
+
+        Self {
+            active: true,
+            scope: Some(to_scope_string(&scopes_from_json(&token.scopes))),
+            client_id: Some(token.application_id),
+            sub: token.user_id.map(|user_id| user_id.to_string()),
+            exp: Some(exp.and_utc().timestamp()),
+            iat: Some(token.created_at.and_utc().timestamp()),
+            token_type: Some(token_type.to_string()),
+            aud: Some(aud),
+            act: token.act.as_deref().and_then(Actor::from_json),
+            cnf: token.dpop_jkt.map(|jkt| Confirmation { jkt }),
+        }
+    }
+}
+
+async fn introspect_access_token(
+    conn: &DatabaseConnection,
+    value: &str,
+) -> Result<Option<IntrospectResponse>, AppError> {
+    let now = chrono::Utc::now().naive_utc();
+
+    Ok(token::Entity::find()
+        .filter(token::Column::AccessToken.eq(value))
+        .one(conn)
+        .await?
+        .map(|token| {
+            if token.expires_at <= now || token.access_token_revoked_at.is_some() {
+                return IntrospectResponse::inactive();
+            }
+
+            let expires_at = token.expires_at;
+            let token_type = match token.dpop_jkt {
+                Some(_) => DPOP_TOKEN_TYPE,
+                None => "Bearer",
+            };
+            IntrospectResponse::active(token, expires_at, token_type)
+        }))
+}
+
+// Refresh tokens are only ever shown to the client they were issued to.
+async fn introspect_refresh_token(
+    conn: &DatabaseConnection,
+    value: &str,
+    client_id: &str,
+) -> Result<Option<IntrospectResponse>, AppError> {
+    let now = chrono::Utc::now().naive_utc();
+
+    Ok(token::Entity::find()
+        .filter(token::Column::RefreshToken.eq(value))
+        .one(conn)
+        .await?
+        .map(|token| match token.refresh_expires_at {
+            Some(refresh_expires_at)
+                if refresh_expires_at > now
+                    && token.refresh_token_used_at.is_none()
+                    && token.application_id == client_id =>
+            {
+                IntrospectResponse::active(token, refresh_expires_at, "refresh_token")
+            }
+            _ => IntrospectResponse::inactive(),
+        }))
+}
+
+pub async fn handler(
+    Extension(conn): Extension<DatabaseConnection>,
+    Extension(oidc_keys): Extension<OidcKeys>,
+    Extension(redis_client): Extension<redis::Client>,
+    headers: HeaderMap,
+    Form(form): Form<IntrospectRequest>,
+) -> Result<impl IntoResponse, OAuthError> {
+    let app = authenticate_client(&conn, &redis_client, &headers, &form.client).await?;
+
+    // Introspection is meant for confidential clients and resource servers.
+    if is_public_client(&app) {
+        return Err(ServiceError::UnauthorizedClient.into());
+    }
+    let access_token = access_token_lookup_key(&oidc_keys, &form.token);
+
+    // The proof is optional: resource servers may relay the one they received,
+    // and then only tokens bound to its key are reported active. Without one,
+    // `cnf` is returned for them to compare themselves.
+    let dpop_jkt = verify_dpop_proof(
+        &redis_client,
+        &headers,
+        DpopRequest {
+            method: "POST",
+            path: INTROSPECTION_ENDPOINT_PATH,
+            access_token: Some(&form.token),
+        },
+    )
+    .await?;
+
+    // The hint only decides which kind of token is looked up first.
+    let response = match form.token_type_hint.as_deref() {
+        Some("refresh_token") => match introspect_refresh_token(&conn, &form.token, &app.id).await?
+        {
+            Some(response) => Some(response),
+            None => introspect_access_token(&conn, &access_token).await?,
+        },
+        _ => match introspect_access_token(&conn, &access_token).await? {
+            Some(response) => Some(response),
+            None => introspect_refresh_token(&conn, &form.token, &app.id).await?,
+        },
+    };
+
+    let response = response.filter(|response| {
+        dpop_jkt.is_none() || response.cnf.as_ref().map(|cnf| &cnf.jkt) == dpop_jkt.as_ref()
+    });
+
+    Ok(Json(response.unwrap_or_else(IntrospectResponse::inactive)))
+}
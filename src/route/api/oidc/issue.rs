@@ -0,0 +1,239 @@
+use chrono::{Duration, NaiveDateTime, Utc};
+use sea_orm::{ActiveModelTrait, ConnectionTrait, DbErr, EntityTrait, Set};
+use serde::Serialize;
+use uuid::Uuid;
+
+use entity::{application, image, token, user};
+
+use super::{
+    access_token::{AccessTokenClaims, ACCESS_TOKEN_JWT_TYPE},
+    claims::UserClaims,
+    client_auth::grant_type_allowed,
+    dpop::{Confirmation, DPOP_TOKEN_TYPE},
+    id_token::{half_hash, IdTokenClaims, PASSWORD_ACR, PASSWORD_AMR},
+    keys::{signing_algorithm, OidcKeys},
+    scope::{scopes_to_json, to_scope_string, Scopes},
+    token_exchange::Actor,
+};
+use crate::{
+    constants::{ACCESS_TOKEN_FORMAT_JWT, ACCESS_TOKEN_FORMAT_OPAQUE, ENVS, PARSED_FRONTEND_URL},
+    error::AppError,
+    model::login_session::record_participant,
+};
+
+pub const ACCESS_TOKEN_EXPIRES_IN: i64 = 3600; // 1 hour in seconds.
+pub const REFRESH_TOKEN_EXPIRES_IN: i64 = 30 * 24 * 3600; // 30 days in seconds.
+pub const OFFLINE_ACCESS_SCOPE: &str = "offline_access";
+
+#[derive(Debug, Serialize)]
+pub struct TokenResponse {
+    pub access_token: String,
+    pub token_type: String,
+    pub expires_in: i64,
+    pub scope: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id_token: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub refresh_token: Option<String>,
+    // Set for token exchange responses
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub issued_token_type: Option<String>,
+}
+
+pub struct IssueTokenParams<'a> {
+    pub app: &'a application::Model,
+    // `None` for machine tokens, which carry neither a refresh token nor an ID token.
+    pub user_id: Option<i32>,
+    pub scopes: Scopes,
+    pub auth_time: Option<NaiveDateTime>,
+    pub nonce: Option<String>,
+    // Tokens rotated out of the same grant share a family; `None` starts a new one.
+    pub family_id: Option<String>,
+    // The login session the tokens belong to, recorded for back-channel logout
+    pub sid: Option<String>,
+    // Set by token exchange for a service other than the client. Such tokens
+    // come without a refresh token or an ID token.
+    pub audience: Option<String>,
+    pub act: Option<Actor>,
+    // The JWK thumbprint of the client's DPoP key, which the tokens are bound to
+    pub dpop_jkt: Option<String>,
+}
+
+pub struct IdTokenParams<'a> {
+    pub app: &'a application::Model,
+    pub user_id: i32,
+    pub scopes: &'a Scopes,
+    pub auth_time: Option<NaiveDateTime>,
+    pub nonce: Option<String>,
+    pub sid: Option<String>,
+    // Issued alongside the ID token, which binds them through `at_hash` and `c_hash`
+    pub access_token: Option<&'a str>,
+    pub code: Option<&'a str>,
+}
+
+pub async fn sign_id_token<C: ConnectionTrait>(
+    db: &C,
+    oidc_keys: &OidcKeys,
+    params: IdTokenParams<'_>,
+) -> Result<String, AppError> {
+    let IdTokenParams {
+        app,
+        user_id,
+        scopes,
+        auth_time,
+        nonce,
+        sid,
+        access_token,
+        code,
+    } = params;
+
+    let (user, face) = user::Entity::find_by_id(user_id)
+        .find_also_related(image::Entity)
+        .one(db)
+        .await?
+        .ok_or_else(|| AppError::from(DbErr::RecordNotFound("User not found".to_string())))?;
+    let alg = signing_algorithm(app.id_token_signed_response_alg.as_deref());
+    let now = Utc::now();
+
+    let claims = IdTokenClaims {
+        iss: PARSED_FRONTEND_URL.to_string(),
+        sub: user.id.to_string(),
+        aud: app.id.clone(),
+        exp: (now + Duration::seconds(ACCESS_TOKEN_EXPIRES_IN)).timestamp() as usize,
+        iat: now.timestamp() as usize,
+        auth_time: auth_time.map(|auth_time| auth_time.and_utc().timestamp() as usize),
+        nonce,
+        amr: vec![PASSWORD_AMR.to_string()],
+        acr: Some(PASSWORD_ACR.to_string()),
+        azp: app.id.clone(),
+        at_hash: access_token.and_then(|access_token| half_hash(alg, access_token)),
+        c_hash: code.and_then(|code| half_hash(alg, code)),
+        sid,
+        user_claims: UserClaims::new(&user, face.as_ref(), scopes),
+    };
+
+    oidc_keys.sign(alg, &claims)
+}
+
+// Mint an access token, plus a refresh token when `offline_access` was granted
+// to a client allowed the refresh_token grant and an ID token when `openid` was, and persist them as a single token row.
+pub async fn issue_tokens<C: ConnectionTrait>(
+    db: &C,
+    oidc_keys: &OidcKeys,
+    params: IssueTokenParams<'_>,
+) -> Result<TokenResponse, AppError> {
+    let IssueTokenParams {
+        app,
+        user_id,
+        scopes,
+        auth_time,
+        nonce,
+        family_id,
+        sid,
+        audience,
+        act,
+        dpop_jkt,
+    } = params;
+
+    let now = Utc::now();
+    let expires_at = now + Duration::seconds(ACCESS_TOKEN_EXPIRES_IN);
+    // The stored value: the opaque token itself, or the `jti` of a JWT access token
+    let token_id = Uuid::new_v4().to_string();
+    let refresh_token = (user_id.is_some()
+        && audience.is_none()
+        && scopes.contains(OFFLINE_ACCESS_SCOPE)
+        && grant_type_allowed(app, "refresh_token"))
+    .then(|| Uuid::new_v4().to_string());
+
+    let access_token_format = match app.access_token_format.as_str() {
+        ACCESS_TOKEN_FORMAT_JWT => ACCESS_TOKEN_FORMAT_JWT,
+        _ => ACCESS_TOKEN_FORMAT_OPAQUE,
+    };
+    let access_token = match access_token_format {
+        ACCESS_TOKEN_FORMAT_JWT => {
+            let claims = AccessTokenClaims {
+                iss: PARSED_FRONTEND_URL.to_string(),
+                // RFC 9068 uses the client itself as subject when no user is involved
+                sub: user_id.map_or_else(|| app.id.clone(), |user_id| user_id.to_string()),
+                // The resource server the token is for (RFC 9068 section 3)
+                aud: audience
+                    .clone()
+                    .unwrap_or_else(|| ENVS.access_token_audience.clone()),
+                exp: expires_at.timestamp() as usize,
+                iat: now.timestamp() as usize,
+                jti: token_id.clone(),
+                client_id: app.id.clone(),
+                scope: to_scope_string(&scopes),
+                auth_time: auth_time.map(|auth_time| auth_time.and_utc().timestamp() as usize),
+                act: act.clone(),
+                cnf: dpop_jkt.clone().map(|jkt| Confirmation { jkt }),
+            };
+            oidc_keys.sign_with_type(
+                signing_algorithm(app.id_token_signed_response_alg.as_deref()),
+                ACCESS_TOKEN_JWT_TYPE,
+                &claims,
+            )?
+        }
+        _ => token_id.clone(),
+    };
+
+    let new_token = token::ActiveModel {
+        access_token: Set(token_id),
+        access_token_format: Set(access_token_format.to_string()),
+        refresh_token: Set(refresh_token.clone()),
+        user_id: Set(user_id),
+        application_id: Set(app.id.clone()),
+        scopes: Set(scopes_to_json(&scopes)),
+        expires_at: Set(expires_at.naive_utc()),
+        created_at: Set(now.naive_utc()),
+        family_id: Set(family_id.unwrap_or_else(|| Uuid::new_v4().to_string())),
+        refresh_expires_at: Set(refresh_token
+            .as_ref()
+            .map(|_| (now + Duration::seconds(REFRESH_TOKEN_EXPIRES_IN)).naive_utc())),
+        auth_time: Set(auth_time),
+        sid: Set(sid.clone()),
+        audience: Set(audience.clone()),
+        act: Set(act.as_ref().map(Actor::to_json)),
+        dpop_jkt: Set(dpop_jkt.clone()),
+        ..Default::default()
+    };
+    new_token.insert(db).await?;
+
+    if let Some(sid) = &sid {
+        record_participant(db, sid, &app.id).await?;
+    }
+
+    let id_token = match user_id.filter(|_| audience.is_none() && scopes.contains("openid")) {
+        Some(user_id) => Some(
+            sign_id_token(
+                db,
+                oidc_keys,
+                IdTokenParams {
+                    app,
+                    user_id,
+                    scopes: &scopes,
+                    auth_time,
+                    nonce,
+                    sid,
+                    access_token: Some(&access_token),
+                    code: None,
+                },
+            )
+            .await?,
+        ),
+        None => None,
+    };
+
+    Ok(TokenResponse {
+        access_token,
+        token_type: match dpop_jkt {
+            Some(_) => DPOP_TOKEN_TYPE.to_string(),
+            None => "Bearer".to_string(),
+        },
+        expires_in: ACCESS_TOKEN_EXPIRES_IN,
+        scope: to_scope_string(&scopes),
+        id_token,
+        refresh_token,
+        issued_token_type: None,
+    })
+}
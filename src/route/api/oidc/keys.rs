@@ -0,0 +1,442 @@
+use std::{
+    str::FromStr,
+    sync::{Arc, RwLock},
+    time::Duration as StdDuration,
+};
+
+use anyhow::anyhow;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{Duration, NaiveDateTime, Utc};
+use jsonwebtoken::{
+    decode, decode_header, encode, jwk::Jwk, Algorithm, DecodingKey, EncodingKey, Header,
+    Validation,
+};
+use openssl::{
+    bn::{BigNum, BigNumContext},
+    ec::{EcGroup, EcKey},
+    error::ErrorStack,
+    hash::{hash, MessageDigest},
+    nid::Nid,
+    pkey::{Id, PKey, Private},
+    rsa::Rsa,
+};
+use sea_orm::DatabaseConnection;
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::{json, Value};
+
+use entity::signing_key;
+
+use crate::{
+    constants::ENVS,
+    error::AppError,
+    model::signing_key::{
+        CreateSigningKeyParams, SigningKeyModel, KEY_STATUS_ACTIVE, KEY_STATUS_PENDING,
+    },
+    util::{decrypt_secret, encrypt_secret},
+};
+
+// A new key is published this long before it starts signing, so relying
+// parties that cache the JWKS already know it when the first token arrives.
+const KEY_PREPUBLISH_TIME: i64 = 24 * 3600;
+// Retired keys stay in the JWKS long enough to outlive any token they signed.
+const RETIRED_KEY_RETENTION: i64 = 7 * 24 * 3600;
+// How often every replica re-reads the key table and runs scheduled rotation.
+const KEY_MAINTENANCE_INTERVAL: u64 = 300;
+
+struct SigningKey {
+    kid: String,
+    alg: Algorithm,
+    status: String,
+    activated_at: Option<NaiveDateTime>,
+    encoding_key: EncodingKey,
+    decoding_key: DecodingKey,
+    public_jwk: Value,
+}
+
+// The published key set shared by every handler; for each algorithm the newest
+// active key signs.
+#[derive(Clone, Default)]
+pub struct OidcKeys(Arc<RwLock<Vec<SigningKey>>>);
+
+pub const SUPPORTED_SIGNING_ALGORITHMS: [Algorithm; 4] = [
+    Algorithm::RS256,
+    Algorithm::PS256,
+    Algorithm::ES256,
+    Algorithm::EdDSA,
+];
+
+lazy_static! {
+    // Algorithms enabled through SIGNING_ALGORITHMS; the first one is the default.
+    pub static ref SIGNING_ALGORITHMS: Vec<Algorithm> = {
+        let algorithms: Vec<Algorithm> = ENVS
+            .signing_algorithms
+            .iter()
+            .map(|alg| {
+                Algorithm::from_str(alg)
+                    .ok()
+                    .filter(|alg| SUPPORTED_SIGNING_ALGORITHMS.contains(alg))
+                    .expect("SIGNING_ALGORITHMS contains an unsupported algorithm")
+            })
+            .collect();
+        assert!(!algorithms.is_empty(), "SIGNING_ALGORITHMS is empty");
+        algorithms
+    };
+}
+
+fn algorithm_name(alg: Algorithm) -> String {
+    json!(alg).as_str().unwrap_or_default().to_string()
+}
+
+// The algorithm an application asked for, as long as it is still enabled.
+pub fn signing_algorithm(requested: Option<&str>) -> Algorithm {
+    requested
+        .and_then(|alg| Algorithm::from_str(alg).ok())
+        .filter(|alg| SIGNING_ALGORITHMS.contains(alg))
+        .unwrap_or(SIGNING_ALGORITHMS[0])
+}
+
+pub fn is_enabled_signing_algorithm(alg: &str) -> bool {
+    Algorithm::from_str(alg).map_or(false, |alg| SIGNING_ALGORITHMS.contains(&alg))
+}
+
+// RS256 and PS256 each get their own RSA key rather than sharing one.
+fn generate_key(alg: Algorithm) -> Result<PKey<Private>, ErrorStack> {
+    match alg {
+        Algorithm::ES256 => {
+            let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1)?;
+            PKey::from_ec_key(EcKey::generate(&group)?)
+        }
+        Algorithm::EdDSA => PKey::generate_ed25519(),
+        _ => PKey::from_rsa(Rsa::generate(2048)?),
+    }
+}
+
+fn encoding_key(alg: Algorithm, pem: &[u8]) -> Result<EncodingKey, AppError> {
+    Ok(match alg {
+        Algorithm::ES256 => EncodingKey::from_ec_pem(pem)?,
+        Algorithm::EdDSA => EncodingKey::from_ed_pem(pem)?,
+        _ => EncodingKey::from_rsa_pem(pem)?,
+    })
+}
+
+// The public members of the JWK for each key type (RFC 7518 and RFC 8037).
+fn public_jwk(key: &PKey<Private>) -> Result<Value, AppError> {
+    Ok(match key.id() {
+        Id::RSA => {
+            let rsa = key.rsa()?;
+            json!({
+                "kty": "RSA",
+                "n": URL_SAFE_NO_PAD.encode(rsa.n().to_vec()),
+                "e": URL_SAFE_NO_PAD.encode(rsa.e().to_vec()),
+            })
+        }
+        Id::EC => {
+            let ec = key.ec_key()?;
+            let mut ctx = BigNumContext::new()?;
+            let mut x = BigNum::new()?;
+            let mut y = BigNum::new()?;
+            ec.public_key()
+                .affine_coordinates(ec.group(), &mut x, &mut y, &mut ctx)?;
+            json!({
+                "kty": "EC",
+                "crv": "P-256",
+                "x": URL_SAFE_NO_PAD.encode(x.to_vec_padded(32)?),
+                "y": URL_SAFE_NO_PAD.encode(y.to_vec_padded(32)?),
+            })
+        }
+        Id::ED25519 => json!({
+            "kty": "OKP",
+            "crv": "Ed25519",
+            "x": URL_SAFE_NO_PAD.encode(key.raw_public_key()?),
+        }),
+        id => return Err(anyhow!("unsupported signing key type {:?}", id).into()),
+    })
+}
+
+// RFC 7638: hash the required public members in lexicographic order.
+pub fn jwk_thumbprint(public_jwk: &Value) -> Result<String, ErrorStack> {
+    let member = |name: &str| public_jwk[name].as_str().unwrap_or_default().to_string();
+    let canonical = match member("kty").as_str() {
+        "EC" => format!(
+            r#"{{"crv":"{}","kty":"EC","x":"{}","y":"{}"}}"#,
+            member("crv"),
+            member("x"),
+            member("y"),
+        ),
+        "OKP" => format!(
+            r#"{{"crv":"{}","kty":"OKP","x":"{}"}}"#,
+            member("crv"),
+            member("x"),
+        ),
+        _ => format!(
+            r#"{{"e":"{}","kty":"RSA","n":"{}"}}"#,
+            member("e"),
+            member("n"),
+        ),
+    };
+
+    Ok(URL_SAFE_NO_PAD.encode(hash(MessageDigest::sha256(), canonical.as_bytes())?))
+}
+
+fn decode_key(model: signing_key::Model) -> Result<SigningKey, AppError> {
+    let pem = decrypt_secret(&model.private_key, model.kid.as_bytes())?
+        .ok_or_else(|| anyhow!("signing key {} is malformed", model.kid))?;
+    let private_key = PKey::private_key_from_pem(&pem)?;
+    let alg = Algorithm::from_str(&model.alg)?;
+    let public_jwk = public_jwk(&private_key)?;
+    let jwk: Jwk = serde_json::from_value(public_jwk.clone())
+        .map_err(|err| anyhow!("signing key {} has an invalid JWK: {}", model.kid, err))?;
+
+    Ok(SigningKey {
+        alg,
+        status: model.status,
+        activated_at: model.activated_at,
+        encoding_key: encoding_key(alg, &pem)?,
+        decoding_key: DecodingKey::from_jwk(&jwk)?,
+        public_jwk,
+        kid: model.kid,
+    })
+}
+
+async fn create_key(
+    conn: &DatabaseConnection,
+    alg: Algorithm,
+    status: &'static str,
+) -> Result<signing_key::Model, AppError> {
+    let private_key = generate_key(alg)?;
+    let kid = jwk_thumbprint(&public_jwk(&private_key)?)?;
+    let pem = private_key.private_key_to_pem_pkcs8()?;
+
+    Ok(SigningKeyModel::new(conn)
+        .insert_key(CreateSigningKeyParams {
+            private_key: encrypt_secret(&pem, kid.as_bytes())?,
+            kid,
+            alg: algorithm_name(alg),
+            status,
+        })
+        .await?)
+}
+
+fn retain_until() -> NaiveDateTime {
+    (Utc::now() + Duration::seconds(RETIRED_KEY_RETENTION)).naive_utc()
+}
+
+impl OidcKeys {
+    pub async fn load(conn: &DatabaseConnection) -> Result<Self, AppError> {
+        let keys = Self::default();
+        keys.maintain(conn).await?;
+        Ok(keys)
+    }
+
+    pub async fn reload(&self, conn: &DatabaseConnection) -> Result<(), AppError> {
+        let mut keys = vec![];
+        for model in SigningKeyModel::new(conn).find_published_keys().await? {
+            let kid = model.kid.clone();
+            match decode_key(model) {
+                Ok(key) => keys.push(key),
+                Err(err) => tracing::error!("Failed to load signing key {}: {:?}", kid, err),
+            }
+        }
+
+        *self.0.write().unwrap() = keys;
+        Ok(())
+    }
+
+    // For every enabled algorithm: bootstrap the first key, promote the pending
+    // key once the active one is older than the rotation period and keep a
+    // pending key published. Then reload the cache.
+    pub async fn maintain(&self, conn: &DatabaseConnection) -> Result<(), AppError> {
+        let model = SigningKeyModel::new(conn);
+        model.delete_expired_keys().await?;
+
+        let enabled: Vec<String> = SIGNING_ALGORITHMS
+            .iter()
+            .map(|alg| algorithm_name(*alg))
+            .collect();
+        model.retire_keys_except(&enabled, retain_until()).await?;
+
+        let keys = model.find_published_keys().await?;
+        let now = Utc::now().naive_utc();
+
+        for (alg, alg_name) in SIGNING_ALGORITHMS.iter().zip(enabled.iter()) {
+            let active = keys
+                .iter()
+                .filter(|key| &key.alg == alg_name && key.status == KEY_STATUS_ACTIVE)
+                .max_by_key(|key| key.activated_at);
+            let pending = keys
+                .iter()
+                .filter(|key| &key.alg == alg_name && key.status == KEY_STATUS_PENDING)
+                .min_by_key(|key| key.created_at);
+
+            let rotation_due = active.map_or(true, |active| {
+                active.activated_at.map_or(true, |activated_at| {
+                    activated_at + Duration::days(ENVS.signing_key_rotation_days) <= now
+                })
+            });
+            let pending_ready = pending.map_or(false, |pending| {
+                active.is_none()
+                    || pending.created_at + Duration::seconds(KEY_PREPUBLISH_TIME) <= now
+            });
+
+            let mut has_pending = pending.is_some();
+            match (active, pending) {
+                (None, None) => {
+                    create_key(conn, *alg, KEY_STATUS_ACTIVE).await?;
+                }
+                (_, Some(pending)) if rotation_due && pending_ready => {
+                    model
+                        .activate_key(&pending.kid, alg_name, retain_until())
+                        .await?;
+                    has_pending = false;
+                }
+                _ => {}
+            }
+
+            if !has_pending {
+                create_key(conn, *alg, KEY_STATUS_PENDING).await?;
+            }
+        }
+
+        self.reload(conn).await
+    }
+
+    // Manual rotation: for every enabled algorithm the pending key starts
+    // signing right away and a fresh pending key takes its place.
+    pub async fn rotate(&self, conn: &DatabaseConnection) -> Result<(), AppError> {
+        let model = SigningKeyModel::new(conn);
+        let keys = model.find_published_keys().await?;
+
+        for alg in SIGNING_ALGORITHMS.iter() {
+            let alg_name = algorithm_name(*alg);
+            let pending = keys
+                .iter()
+                .filter(|key| key.alg == alg_name && key.status == KEY_STATUS_PENDING)
+                .min_by_key(|key| key.created_at)
+                .cloned();
+
+            let next = match pending {
+                Some(pending) => pending,
+                None => create_key(conn, *alg, KEY_STATUS_PENDING).await?,
+            };
+            model
+                .activate_key(&next.kid, &alg_name, retain_until())
+                .await?;
+            tracing::info!("Signing key {} is now active for {}", next.kid, alg_name);
+
+            create_key(conn, *alg, KEY_STATUS_PENDING).await?;
+        }
+
+        self.reload(conn).await
+    }
+
+    pub fn spawn_maintenance(&self, conn: DatabaseConnection) {
+        let keys = self.clone();
+        tokio::spawn(async move {
+            let mut interval =
+                tokio::time::interval(StdDuration::from_secs(KEY_MAINTENANCE_INTERVAL));
+            // The first tick fires immediately and `load` has just run.
+            interval.tick().await;
+            loop {
+                interval.tick().await;
+                if let Err(err) = keys.maintain(&conn).await {
+                    tracing::error!("Signing key maintenance failed: {:?}", err);
+                }
+            }
+        });
+    }
+
+    pub fn sign<T: Serialize>(&self, alg: Algorithm, claims: &T) -> Result<String, AppError> {
+        self.sign_with_type(alg, "JWT", claims)
+    }
+
+    pub fn sign_with_type<T: Serialize>(
+        &self,
+        alg: Algorithm,
+        typ: &str,
+        claims: &T,
+    ) -> Result<String, AppError> {
+        let keys = self.0.read().unwrap();
+        let key = keys
+            .iter()
+            .filter(|key| key.alg == alg && key.status == KEY_STATUS_ACTIVE)
+            .max_by_key(|key| key.activated_at)
+            .ok_or_else(|| anyhow!("no active signing key for {:?}", alg))?;
+
+        let mut header = Header::new(key.alg);
+        header.typ = Some(typ.to_string());
+        header.kid = Some(key.kid.clone());
+
+        Ok(encode(&header, claims, &key.encoding_key)?)
+    }
+
+    // Check that `token` was signed by one of our published keys. Expiry and
+    // audience are left to the caller.
+    pub fn verify<T: DeserializeOwned>(&self, token: &str) -> Result<T, AppError> {
+        let header = decode_header(token)?;
+        let keys = self.0.read().unwrap();
+        let key = keys
+            .iter()
+            .find(|key| Some(&key.kid) == header.kid.as_ref())
+            .ok_or_else(|| anyhow!("unknown signing key {:?}", header.kid))?;
+
+        let mut validation = Validation::new(key.alg);
+        validation.validate_exp = false;
+        validation.validate_aud = false;
+        validation.required_spec_claims.clear();
+
+        Ok(decode::<T>(token, &key.decoding_key, &validation)?.claims)
+    }
+
+    pub fn jwks(&self) -> Value {
+        let keys: Vec<Value> = self
+            .0
+            .read()
+            .unwrap()
+            .iter()
+            .map(|key| {
+                let mut jwk = key.public_jwk.clone();
+                jwk["use"] = json!("sig");
+                jwk["alg"] = json!(key.alg);
+                jwk["kid"] = json!(key.kid);
+                jwk
+            })
+            .collect();
+
+        json!({ "keys": keys })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::jwk_thumbprint;
+
+    #[test]
+    fn thumbprint_matches_rfc7638_example() {
+        let jwk = json!({
+            "kty": "RSA",
+            "n": "0vx7agoebGcQSuuPiLJXZptN9nndrQmbXEps2aiAFbWhM78LhWx4cbbfAAtVT86zwu1RK7aPFFxuhDR1L6tSoc_BJECPebWKRXjBZCiFV4n3oknjhMstn64tZ_2W-5JsGY4Hc5n9yBXArwl93lqt7_RN5w6Cf0h4QyQ5v-65YGjQR0_FDW2QvzqY368QQMicAtaSqzs8KJZgnYb9c7d0zgdAZHzu6qMQvRL5hajrn1n91CbOpbISD08qNLyrdkt-bFTWhAI4vMQFh6WeZu0fM4lFd2NcRwr3XPksINHaQ-G_xBniIqbw0Ls1jF44-csFCur-kEgU8awapJzKnqDKgw",
+            "e": "AQAB",
+        });
+
+        assert_eq!(
+            jwk_thumbprint(&jwk).unwrap(),
+            "NzbLsXh8uDCcd-6MNwXF4W_7noWXFZAfHkxZsRGC9Xs"
+        );
+    }
+
+    #[test]
+    fn thumbprint_matches_rfc8037_example() {
+        let jwk = json!({
+            "kty": "OKP",
+            "crv": "Ed25519",
+            "x": "11qYAYKxCrfVS_7TyWQHOg7hcvPapiMlrwIaaPcHURo",
+        });
+
+        assert_eq!(
+            jwk_thumbprint(&jwk).unwrap(),
+            "kPrK_qmxVWaYVA9wwBF6Iuo3vVzz7TxHCTwXBygrS4k"
+        );
+    }
+}
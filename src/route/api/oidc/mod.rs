@@ -1,4 +1,26 @@
+pub mod access_token;
 pub mod authorize;
+pub mod backchannel_logout;
+pub mod claims;
+pub mod client_auth;
+pub mod consent;
+pub mod denylist;
+pub mod device;
+pub mod dpop;
+pub mod end_session;
+pub mod frontchannel_logout;
+pub mod id_token;
+pub mod introspect;
+pub mod issue;
+pub mod keys;
+pub mod par;
+pub mod pkce;
+pub mod register;
+pub mod request_object;
+pub mod response;
+pub mod revoke;
+pub mod scope;
 pub mod token;
+pub mod token_exchange;
 pub mod userinfo;
 pub mod well_known;
@@ -0,0 +1,138 @@
+use axum::{
+    extract::{Extension, Form},
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Json},
+};
+use redis::{AsyncCommands, RedisResult};
+use sea_orm::DatabaseConnection;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    authorize::{validate_authorization_params, AuthorizationParams},
+    client_auth::{authenticate_client, ClientAuthParams},
+    request_object::verify_request_object,
+};
+use crate::{constants::PUSHED_AUTHORIZATION_REQUEST_REDIS_KEY, error::OAuthError};
+
+const REQUEST_URI_PREFIX: &str = "urn:ietf:params:oauth:request_uri:";
+const REQUEST_URI_EXPIRES_IN: usize = 60;
+// Once the browser has arrived, signing in and consenting may take a while
+const REQUEST_URI_LOGIN_EXPIRES_IN: usize = 10 * 60;
+
+#[derive(Debug, Deserialize)]
+pub struct PushedAuthorizationForm {
+    request: Option<String>,
+    #[serde(flatten)]
+    params: AuthorizationParams,
+    #[serde(flatten)]
+    client: ClientAuthParams,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct PushedAuthorizationRequest {
+    client_id: String,
+    params: AuthorizationParams,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PushedAuthorizationResponse {
+    request_uri: String,
+    expires_in: usize,
+}
+
+pub fn is_pushed_request_uri(request_uri: &str) -> bool {
+    request_uri.starts_with(REQUEST_URI_PREFIX)
+}
+
+fn request_uri_key(request_uri: &str) -> Option<String> {
+    request_uri
+        .strip_prefix(REQUEST_URI_PREFIX)
+        .map(|id| format!("{}:{}", PUSHED_AUTHORIZATION_REQUEST_REDIS_KEY, id))
+}
+
+// The pushed parameters behind `request_uri`, as long as they were pushed by
+// the same client and have not expired.
+pub async fn resolve_request_uri(
+    redis_client: &redis::Client,
+    client_id: &str,
+    request_uri: &str,
+) -> RedisResult<Option<AuthorizationParams>> {
+    let Some(key) = request_uri_key(request_uri) else {
+        return Ok(None);
+    };
+
+    let mut conn = redis_client.get_async_connection().await?;
+    let value: Option<String> = conn.get(&key).await?;
+
+    Ok(value
+        .and_then(|value| serde_json::from_str::<PushedAuthorizationRequest>(&value).ok())
+        .filter(|pushed| pushed.client_id == client_id)
+        .map(|pushed| pushed.params))
+}
+
+// Keep the request around while the user signs in or consents.
+pub async fn extend_request_uri(
+    redis_client: &redis::Client,
+    request_uri: &str,
+) -> RedisResult<()> {
+    if let Some(key) = request_uri_key(request_uri) {
+        let mut conn = redis_client.get_async_connection().await?;
+        let _: () = conn.expire(&key, REQUEST_URI_LOGIN_EXPIRES_IN).await?;
+    }
+
+    Ok(())
+}
+
+// A request URI is used up once it produced an authorization code.
+pub async fn consume_request_uri(
+    redis_client: &redis::Client,
+    request_uri: &str,
+) -> RedisResult<()> {
+    if let Some(key) = request_uri_key(request_uri) {
+        let mut conn = redis_client.get_async_connection().await?;
+        let _: () = conn.del(&key).await?;
+    }
+
+    Ok(())
+}
+
+pub async fn handler(
+    Extension(conn): Extension<DatabaseConnection>,
+    Extension(redis_client): Extension<redis::Client>,
+    headers: HeaderMap,
+    Form(form): Form<PushedAuthorizationForm>,
+) -> Result<impl IntoResponse, OAuthError> {
+    let app = authenticate_client(&conn, &redis_client, &headers, &form.client).await?;
+    let params = match &form.request {
+        Some(request) => form
+            .params
+            .overridden_by(verify_request_object(&conn, &redis_client, &app, request).await?),
+        None => form.params,
+    };
+
+    // Check the parameters now so the client learns about mistakes before the redirect
+    let pushed = PushedAuthorizationRequest {
+        client_id: app.id.clone(),
+        params,
+    };
+    let serialized = serde_json::to_string(&pushed).unwrap();
+    validate_authorization_params(&app, pushed.params).map_err(|err| err.error)?;
+
+    let request_uri = format!("{}{}", REQUEST_URI_PREFIX, uuid::Uuid::new_v4());
+    let mut redis_conn = redis_client.get_async_connection().await?;
+    let _: () = redis_conn
+        .set_ex(
+            request_uri_key(&request_uri).unwrap(),
+            serialized,
+            REQUEST_URI_EXPIRES_IN,
+        )
+        .await?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(PushedAuthorizationResponse {
+            request_uri,
+            expires_in: REQUEST_URI_EXPIRES_IN,
+        }),
+    ))
+}
@@ -0,0 +1,58 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use openssl::{
+    hash::{hash, MessageDigest},
+    memcmp,
+};
+
+pub const CODE_CHALLENGE_METHODS: [&str; 2] = ["S256", "plain"];
+
+// RFC 7636 section 4.1: 43 to 128 characters from the unreserved set
+fn is_valid_code_value(value: &str) -> bool {
+    (43..=128).contains(&value.len())
+        && value
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '.' | '_' | '~'))
+}
+
+pub fn is_valid_code_challenge(challenge: &str, method: &str) -> bool {
+    CODE_CHALLENGE_METHODS.contains(&method) && is_valid_code_value(challenge)
+}
+
+pub fn verify_code_verifier(verifier: &str, challenge: &str, method: &str) -> bool {
+    if !is_valid_code_value(verifier) {
+        return false;
+    }
+
+    let computed = match method {
+        "S256" => match hash(MessageDigest::sha256(), verifier.as_bytes()) {
+            Ok(digest) => URL_SAFE_NO_PAD.encode(digest),
+            Err(_) => return false,
+        },
+        "plain" => verifier.to_string(),
+        _ => return false,
+    };
+
+    computed.len() == challenge.len() && memcmp::eq(computed.as_bytes(), challenge.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::verify_code_verifier;
+
+    // Example from RFC 7636 appendix B
+    const VERIFIER: &str = "dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk";
+    const CHALLENGE: &str = "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM";
+
+    #[test]
+    fn s256_verifier_matches_rfc_example() {
+        assert!(verify_code_verifier(VERIFIER, CHALLENGE, "S256"));
+        assert!(!verify_code_verifier(VERIFIER, VERIFIER, "S256"));
+    }
+
+    #[test]
+    fn plain_verifier_must_equal_challenge() {
+        assert!(verify_code_verifier(VERIFIER, VERIFIER, "plain"));
+        assert!(!verify_code_verifier(VERIFIER, CHALLENGE, "plain"));
+        assert!(!verify_code_verifier("too-short", "too-short", "plain"));
+    }
+}
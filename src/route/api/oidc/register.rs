@@ -0,0 +1,491 @@
+use axum::{
+    extract::{Extension, Path},
+    http::{header::AUTHORIZATION, HeaderMap, StatusCode},
+    response::{IntoResponse, Json},
+};
+use jsonwebtoken::jwk::JwkSet;
+use sea_orm::DatabaseConnection;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use entity::application;
+
+use super::{
+    client_auth::TOKEN_ENDPOINT_AUTH_METHODS,
+    keys::is_enabled_signing_algorithm,
+    response::{
+        allowed_response_types, normalize_response_type, response_type_includes, RESPONSE_TYPES,
+        RESPONSE_TYPE_CODE,
+    },
+    scope::{parse_scope, scopes_from_json, to_scope_string},
+    token::GRANT_TYPES,
+    token_exchange::TOKEN_EXCHANGE_GRANT_TYPE,
+};
+use crate::{
+    constants::{
+        AUTH_METHOD_CLIENT_SECRET_BASIC, AUTH_METHOD_CLIENT_SECRET_JWT,
+        AUTH_METHOD_CLIENT_SECRET_POST, AUTH_METHOD_NONE, AUTH_METHOD_PRIVATE_KEY_JWT,
+        PARSED_FRONTEND_URL,
+    },
+    error::{AppError, OAuthError, ServiceError},
+    model::{
+        application::{ApplicationModel, ClientMetadataParams, RegisterApplicationParams},
+        application_secret::{ApplicationSecretModel, CreateSecretParams},
+        initial_access_token::InitialAccessTokenModel,
+    },
+};
+
+const MAX_CLIENT_NAME_LENGTH: usize = 24;
+
+#[derive(Debug, Deserialize)]
+pub struct ClientMetadataRequest {
+    // Only sent back on updates, where it has to match the path
+    client_id: Option<String>,
+    #[serde(default)]
+    redirect_uris: Vec<String>,
+    grant_types: Option<Vec<String>>,
+    response_types: Option<Vec<String>>,
+    token_endpoint_auth_method: Option<String>,
+    client_name: Option<String>,
+    client_uri: Option<String>,
+    scope: Option<String>,
+    jwks: Option<Value>,
+    jwks_uri: Option<String>,
+    id_token_signed_response_alg: Option<String>,
+    userinfo_signed_response_alg: Option<String>,
+    post_logout_redirect_uris: Option<Vec<String>>,
+    backchannel_logout_uri: Option<String>,
+    frontchannel_logout_uri: Option<String>,
+    require_pushed_authorization_requests: Option<bool>,
+    request_uris: Option<Vec<String>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ClientInformationResponse {
+    client_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    client_secret: Option<String>,
+    client_id_issued_at: i64,
+    // Secrets never expire on their own, which RFC 7591 spells as 0
+    #[serde(skip_serializing_if = "Option::is_none")]
+    client_secret_expires_at: Option<i64>,
+    registration_access_token: String,
+    registration_client_uri: String,
+    client_name: String,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    client_uri: String,
+    redirect_uris: Vec<String>,
+    grant_types: Vec<String>,
+    response_types: Vec<String>,
+    token_endpoint_auth_method: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    scope: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    jwks: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    jwks_uri: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    id_token_signed_response_alg: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    userinfo_signed_response_alg: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    post_logout_redirect_uris: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    backchannel_logout_uri: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    frontchannel_logout_uri: Option<String>,
+    require_pushed_authorization_requests: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    request_uris: Option<Vec<String>>,
+}
+
+#[derive(Deserialize)]
+pub struct RegistrationUrlParams {
+    pub client_id: String,
+}
+
+fn invalid_client_metadata() -> AppError {
+    AppError::ServiceError(ServiceError::InvalidClientMetadata)
+}
+
+fn uses_client_secret(token_endpoint_auth_method: &str) -> bool {
+    [
+        AUTH_METHOD_CLIENT_SECRET_POST,
+        AUTH_METHOD_CLIENT_SECRET_BASIC,
+        AUTH_METHOD_CLIENT_SECRET_JWT,
+    ]
+    .contains(&token_endpoint_auth_method)
+}
+
+pub fn is_valid_redirect_uri(redirect_uri: &str) -> bool {
+    url::Url::parse(redirect_uri).map_or(false, |url| {
+        (url.scheme() == "https" || url.scheme() == "http") && url.fragment().is_none()
+    })
+}
+
+fn is_valid_alg(alg: Option<&str>) -> bool {
+    alg.map_or(true, is_enabled_signing_algorithm)
+}
+
+impl ClientMetadataRequest {
+    // Check the metadata against what this provider supports, filling in the
+    // RFC 7591 defaults for anything left out.
+    fn into_metadata(self) -> Result<ClientMetadataParams, AppError> {
+        let grant_types = self
+            .grant_types
+            .unwrap_or_else(|| vec!["authorization_code".to_string()]);
+        if grant_types.is_empty()
+            || !grant_types
+                .iter()
+                .all(|grant_type| GRANT_TYPES.contains(&grant_type.as_str()))
+        {
+            return Err(invalid_client_metadata());
+        }
+        let uses_code = grant_types
+            .iter()
+            .any(|grant_type| grant_type == "authorization_code");
+
+        // Response types returning a code need the authorization_code grant to redeem it
+        let response_types: Vec<String> = self
+            .response_types
+            .unwrap_or_else(|| match uses_code {
+                true => vec![RESPONSE_TYPE_CODE.to_string()],
+                false => vec![],
+            })
+            .iter()
+            .map(|response_type| normalize_response_type(response_type))
+            .collect();
+        let returns_code = response_types
+            .iter()
+            .any(|response_type| response_type_includes(response_type, RESPONSE_TYPE_CODE));
+        if !response_types
+            .iter()
+            .all(|response_type| RESPONSE_TYPES.contains(&response_type.as_str()))
+            || (uses_code && response_types.is_empty())
+            || (returns_code && !uses_code)
+        {
+            return Err(invalid_client_metadata());
+        }
+
+        if (!response_types.is_empty() && self.redirect_uris.is_empty())
+            || !self
+                .redirect_uris
+                .iter()
+                .all(|redirect_uri| is_valid_redirect_uri(redirect_uri))
+        {
+            return Err(AppError::ServiceError(ServiceError::InvalidRedirectUri));
+        }
+
+        if self
+            .post_logout_redirect_uris
+            .iter()
+            .flatten()
+            .any(|redirect_uri| !is_valid_redirect_uri(redirect_uri))
+        {
+            return Err(AppError::ServiceError(ServiceError::InvalidRedirectUri));
+        }
+
+        // Same rules as redirect URIs: absolute http(s) without a fragment
+        if !self
+            .backchannel_logout_uri
+            .as_deref()
+            .map_or(true, is_valid_redirect_uri)
+            || !self
+                .frontchannel_logout_uri
+                .as_deref()
+                .map_or(true, is_valid_redirect_uri)
+        {
+            return Err(invalid_client_metadata());
+        }
+
+        // Request objects are only fetched over https
+        if self.request_uris.iter().flatten().any(|request_uri| {
+            url::Url::parse(request_uri).map_or(true, |url| url.scheme() != "https")
+        }) {
+            return Err(invalid_client_metadata());
+        }
+
+        let token_endpoint_auth_method = self
+            .token_endpoint_auth_method
+            .unwrap_or_else(|| AUTH_METHOD_CLIENT_SECRET_BASIC.to_string());
+        if !TOKEN_ENDPOINT_AUTH_METHODS.contains(&token_endpoint_auth_method.as_str()) {
+            return Err(invalid_client_metadata());
+        }
+        // Public clients cannot hold the credentials these grants rest on
+        if token_endpoint_auth_method == AUTH_METHOD_NONE
+            && grant_types.iter().any(|grant_type| {
+                grant_type == "client_credentials" || grant_type == TOKEN_EXCHANGE_GRANT_TYPE
+            })
+        {
+            return Err(invalid_client_metadata());
+        }
+
+        if self.jwks.is_some() && self.jwks_uri.is_some() {
+            return Err(invalid_client_metadata());
+        }
+        let jwks = match self.jwks {
+            Some(jwks) => {
+                serde_json::from_value::<JwkSet>(jwks.clone())
+                    .map_err(|_| invalid_client_metadata())?;
+                Some(jwks.to_string())
+            }
+            None => None,
+        };
+        if self.jwks_uri.as_deref().map_or(false, |jwks_uri| {
+            url::Url::parse(jwks_uri).map_or(true, |url| url.scheme() != "https")
+        }) {
+            return Err(invalid_client_metadata());
+        }
+        if token_endpoint_auth_method == AUTH_METHOD_PRIVATE_KEY_JWT
+            && jwks.is_none()
+            && self.jwks_uri.is_none()
+        {
+            return Err(invalid_client_metadata());
+        }
+
+        let client_name = self.client_name.unwrap_or_default();
+        let client_uri = self.client_uri.unwrap_or_default();
+        if client_name.chars().count() > MAX_CLIENT_NAME_LENGTH
+            || client_name.chars().any(char::is_control)
+            || (!client_uri.is_empty() && url::Url::parse(&client_uri).is_err())
+            || !is_valid_alg(self.id_token_signed_response_alg.as_deref())
+            || !is_valid_alg(self.userinfo_signed_response_alg.as_deref())
+        {
+            return Err(invalid_client_metadata());
+        }
+
+        Ok(ClientMetadataParams {
+            name: client_name,
+            homepage_url: client_uri,
+            redirect_uris: self.redirect_uris,
+            grant_types,
+            response_types,
+            allowed_scopes: self
+                .scope
+                .map(|scope| parse_scope(&scope).into_iter().collect()),
+            token_endpoint_auth_method,
+            jwks,
+            jwks_uri: self.jwks_uri,
+            id_token_signed_response_alg: self.id_token_signed_response_alg,
+            userinfo_signed_response_alg: self.userinfo_signed_response_alg,
+            post_logout_redirect_uris: self.post_logout_redirect_uris,
+            backchannel_logout_uri: self.backchannel_logout_uri,
+            frontchannel_logout_uri: self.frontchannel_logout_uri,
+            require_pushed_authorization_requests: self
+                .require_pushed_authorization_requests
+                .unwrap_or(false),
+            request_uris: self.request_uris,
+        })
+    }
+}
+
+fn bearer_token(headers: &HeaderMap) -> Result<&str, AppError> {
+    headers
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .ok_or(AppError::ServiceError(ServiceError::InvalidToken))
+}
+
+// The registration access token only ever grants access to its own client.
+async fn find_registered_application(
+    conn: &DatabaseConnection,
+    headers: &HeaderMap,
+    client_id: &str,
+) -> Result<application::Model, AppError> {
+    ApplicationModel::new(conn)
+        .find_one_application_by_registration_access_token(bearer_token(headers)?)
+        .await?
+        .filter(|app| app.id == client_id)
+        .ok_or(AppError::ServiceError(ServiceError::InvalidToken))
+}
+
+async fn latest_client_secret(
+    conn: &DatabaseConnection,
+    app: &application::Model,
+) -> Result<Option<String>, AppError> {
+    if !uses_client_secret(&app.token_endpoint_auth_method) {
+        return Ok(None);
+    }
+
+    let secret_model = ApplicationSecretModel::new(conn);
+    let latest = secret_model
+        .get_secrets_by_application_id(&app.id)
+        .await?
+        .into_iter()
+        .max_by_key(|secret| secret.created_at);
+
+    // Clients switching to a secret-based method get their first secret here
+    let secret = match latest {
+        Some(secret) => secret,
+        None => {
+            secret_model
+                .create_secret_by_application_id(CreateSecretParams {
+                    secret: uuid::Uuid::new_v4().to_string(),
+                    app_id: app.id.clone(),
+                    creator_id: app.creator_id,
+                })
+                .await?
+        }
+    };
+
+    Ok(Some(secret.secret))
+}
+
+fn client_information(
+    app: application::Model,
+    client_secret: Option<String>,
+) -> ClientInformationResponse {
+    let grant_types: Vec<String> = serde_json::from_str(&app.grant_types).unwrap_or_default();
+    let response_types = allowed_response_types(&app);
+
+    ClientInformationResponse {
+        registration_client_uri: format!("{}api/oidc/register/{}", *PARSED_FRONTEND_URL, app.id),
+        client_id: app.id,
+        client_secret_expires_at: client_secret.as_ref().map(|_| 0),
+        client_secret,
+        client_id_issued_at: app.created_at.and_utc().timestamp(),
+        registration_access_token: app.registration_access_token.unwrap_or_default(),
+        client_name: app.name,
+        client_uri: app.homepage_url,
+        redirect_uris: serde_json::from_str(&app.redirect_uris).unwrap_or_default(),
+        grant_types,
+        response_types,
+        token_endpoint_auth_method: app.token_endpoint_auth_method,
+        scope: app
+            .allowed_scopes
+            .map(|allowed_scopes| to_scope_string(&scopes_from_json(&allowed_scopes))),
+        jwks: app.jwks.and_then(|jwks| serde_json::from_str(&jwks).ok()),
+        jwks_uri: app.jwks_uri,
+        id_token_signed_response_alg: app.id_token_signed_response_alg,
+        userinfo_signed_response_alg: app.userinfo_signed_response_alg,
+        post_logout_redirect_uris: app.post_logout_redirect_uris.and_then(
+            |post_logout_redirect_uris| serde_json::from_str(&post_logout_redirect_uris).ok(),
+        ),
+        backchannel_logout_uri: app.backchannel_logout_uri,
+        frontchannel_logout_uri: app.frontchannel_logout_uri,
+        require_pushed_authorization_requests: app.require_pushed_authorization_requests != 0,
+        request_uris: app
+            .request_uris
+            .and_then(|request_uris| serde_json::from_str(&request_uris).ok()),
+    }
+}
+
+pub async fn post_handler(
+    Extension(conn): Extension<DatabaseConnection>,
+    headers: HeaderMap,
+    Json(request): Json<ClientMetadataRequest>,
+) -> Result<impl IntoResponse, OAuthError> {
+    let initial_access_token = InitialAccessTokenModel::new(&conn)
+        .find_unexpired_token(bearer_token(&headers)?)
+        .await?
+        .ok_or(AppError::ServiceError(ServiceError::InvalidToken))?;
+
+    let metadata = request.into_metadata()?;
+
+    let app = ApplicationModel::new(&conn)
+        .insert_registered_application(RegisterApplicationParams {
+            id: uuid::Uuid::new_v4().to_string(),
+            creator_id: initial_access_token.user_id,
+            registration_access_token: uuid::Uuid::new_v4().to_string(),
+            metadata,
+        })
+        .await?;
+    let client_secret = latest_client_secret(&conn, &app).await?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(client_information(app, client_secret)),
+    ))
+}
+
+pub async fn get_handler(
+    Extension(conn): Extension<DatabaseConnection>,
+    Path(url_params): Path<RegistrationUrlParams>,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse, OAuthError> {
+    let app = find_registered_application(&conn, &headers, &url_params.client_id).await?;
+    let client_secret = latest_client_secret(&conn, &app).await?;
+
+    Ok(Json(client_information(app, client_secret)))
+}
+
+pub async fn put_handler(
+    Extension(conn): Extension<DatabaseConnection>,
+    Path(url_params): Path<RegistrationUrlParams>,
+    headers: HeaderMap,
+    Json(request): Json<ClientMetadataRequest>,
+) -> Result<impl IntoResponse, OAuthError> {
+    let app = find_registered_application(&conn, &headers, &url_params.client_id).await?;
+
+    if request.client_id.as_deref() != Some(app.id.as_str()) {
+        return Err(invalid_client_metadata().into());
+    }
+
+    let metadata = request.into_metadata()?;
+
+    let app = ApplicationModel::new(&conn)
+        .replace_client_metadata(app.into(), metadata)
+        .await?;
+    let client_secret = latest_client_secret(&conn, &app).await?;
+
+    Ok(Json(client_information(app, client_secret)))
+}
+
+pub async fn delete_handler(
+    Extension(conn): Extension<DatabaseConnection>,
+    Path(url_params): Path<RegistrationUrlParams>,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse, OAuthError> {
+    let app = find_registered_application(&conn, &headers, &url_params.client_id).await?;
+
+    ApplicationModel::new(&conn)
+        .delete_application(&app.id)
+        .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ClientMetadataRequest;
+
+    fn request(value: serde_json::Value) -> ClientMetadataRequest {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn metadata_defaults_follow_rfc_7591() {
+        let metadata = request(serde_json::json!({
+            "redirect_uris": ["https://client.example.org/callback"],
+        }))
+        .into_metadata()
+        .unwrap();
+
+        assert_eq!(metadata.grant_types, vec!["authorization_code"]);
+        assert_eq!(metadata.token_endpoint_auth_method, "client_secret_basic");
+    }
+
+    #[test]
+    fn inconsistent_metadata_is_rejected() {
+        // The code grant needs somewhere to send the code
+        assert!(request(serde_json::json!({})).into_metadata().is_err());
+        assert!(request(serde_json::json!({
+            "redirect_uris": ["https://client.example.org/callback#fragment"],
+        }))
+        .into_metadata()
+        .is_err());
+        assert!(request(serde_json::json!({
+            "grant_types": ["client_credentials"],
+            "token_endpoint_auth_method": "none",
+        }))
+        .into_metadata()
+        .is_err());
+        assert!(request(serde_json::json!({
+            "grant_types": ["client_credentials"],
+            "token_endpoint_auth_method": "private_key_jwt",
+        }))
+        .into_metadata()
+        .is_err());
+    }
+}
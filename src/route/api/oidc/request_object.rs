@@ -0,0 +1,151 @@
+use jsonwebtoken::{decode, decode_header, Algorithm, DecodingKey, Validation};
+use sea_orm::{ConnectionTrait, ModelTrait};
+use serde::Deserialize;
+
+use entity::{application, application_secret};
+
+use super::{
+    authorize::AuthorizationParams,
+    client_auth::{client_public_keys, CLIENT_ASSERTION_SIGNING_ALGORITHMS},
+};
+use crate::{
+    constants::PARSED_FRONTEND_URL,
+    error::{AppError, ServiceError},
+    http_client::fetch,
+};
+
+#[derive(Debug, Deserialize)]
+struct RequestObjectClaims {
+    client_id: Option<String>,
+    #[serde(flatten)]
+    params: AuthorizationParams,
+}
+
+fn invalid_request_object() -> AppError {
+    AppError::ServiceError(ServiceError::InvalidRequestObject)
+}
+
+// The URL has to be a registered `request_uris` entry, or start with one on
+// the same origin.
+fn is_registered_request_uri(registered: &str, url: &url::Url) -> bool {
+    let Ok(mut registered) = url::Url::parse(registered) else {
+        return false;
+    };
+    registered.set_fragment(None);
+
+    registered.origin() == url.origin() && url.as_str().starts_with(registered.as_str())
+}
+
+// Download a request object the client published at `request_uri`. Only
+// locations the client registered are fetched.
+pub async fn fetch_request_object(
+    app: &application::Model,
+    request_uri: &str,
+) -> Result<String, AppError> {
+    let mut url = url::Url::parse(request_uri).map_err(|_| invalid_request_object())?;
+    // The fragment is only a cache hint for the provider
+    url.set_fragment(None);
+
+    let registered: Vec<String> = app
+        .request_uris
+        .as_deref()
+        .and_then(|request_uris| serde_json::from_str(request_uris).ok())
+        .unwrap_or_default();
+    if url.scheme() != "https"
+        || !registered
+            .iter()
+            .any(|registered| is_registered_request_uri(registered, &url))
+    {
+        return Err(invalid_request_object());
+    }
+
+    let body = fetch(url.as_str()).await.map_err(|err| {
+        tracing::warn!(
+            "Fetching request object from {} failed: {}",
+            request_uri,
+            err
+        );
+        invalid_request_object()
+    })?;
+
+    String::from_utf8(body.to_vec())
+        .map(|body| body.trim().to_string())
+        .map_err(|_| invalid_request_object())
+}
+
+// Check a signed request object (RFC 9101) and return the parameters it
+// carries. HMAC objects are keyed with the client's secrets, the others with
+// its registered public keys.
+pub async fn verify_request_object<C: ConnectionTrait>(
+    db: &C,
+    redis_client: &redis::Client,
+    app: &application::Model,
+    token: &str,
+) -> Result<AuthorizationParams, AppError> {
+    let header = decode_header(token).map_err(|_| invalid_request_object())?;
+    if !CLIENT_ASSERTION_SIGNING_ALGORITHMS.contains(&header.alg) {
+        return Err(invalid_request_object());
+    }
+
+    let mut validation = Validation::new(header.alg);
+    validation.set_audience(&[PARSED_FRONTEND_URL.to_string()]);
+    validation.set_issuer(&[&app.id]);
+    validation.set_required_spec_claims(&["exp", "iss", "aud"]);
+
+    let decoding_keys = match header.alg {
+        Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512 => app
+            .find_related(application_secret::Entity)
+            .all(db)
+            .await?
+            .into_iter()
+            .map(|secret| DecodingKey::from_secret(secret.secret.as_bytes()))
+            .collect::<Vec<_>>(),
+        _ => client_public_keys(redis_client, app)
+            .await
+            .map_err(|_| invalid_request_object())?
+            .iter()
+            .filter(|jwk| header.kid.is_none() || jwk.common.key_id == header.kid)
+            .filter_map(|jwk| DecodingKey::from_jwk(jwk).ok())
+            .collect(),
+    };
+
+    let claims = decoding_keys
+        .iter()
+        .find_map(|key| decode::<RequestObjectClaims>(token, key, &validation).ok())
+        .ok_or_else(invalid_request_object)?
+        .claims;
+
+    if claims
+        .client_id
+        .as_ref()
+        .map_or(false, |client_id| *client_id != app.id)
+    {
+        return Err(invalid_request_object());
+    }
+
+    Ok(claims.params)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::is_registered_request_uri;
+
+    #[test]
+    fn request_uri_must_be_registered() {
+        let url = url::Url::parse("https://client.example.com/requests/abc").unwrap();
+
+        assert!(is_registered_request_uri(
+            "https://client.example.com/requests/abc",
+            &url
+        ));
+        assert!(is_registered_request_uri(
+            "https://client.example.com/requests/",
+            &url
+        ));
+        assert!(!is_registered_request_uri(
+            "https://client.example.com/other/",
+            &url
+        ));
+        assert!(!is_registered_request_uri("https://client.example", &url));
+    }
+}
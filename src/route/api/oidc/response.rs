@@ -0,0 +1,137 @@
+use axum::response::{Html, IntoResponse, Redirect, Response};
+
+use entity::application;
+
+use super::frontchannel_logout::escape_html;
+
+pub const RESPONSE_TYPE_CODE: &str = "code";
+pub const RESPONSE_TYPE_ID_TOKEN: &str = "id_token";
+pub const RESPONSE_TYPE_TOKEN: &str = "token";
+pub const RESPONSE_TYPES: [&str; 4] = ["code", "id_token", "code id_token", "code token"];
+
+pub const RESPONSE_MODE_QUERY: &str = "query";
+pub const RESPONSE_MODE_FRAGMENT: &str = "fragment";
+pub const RESPONSE_MODE_FORM_POST: &str = "form_post";
+pub const RESPONSE_MODES: [&str; 3] = [
+    RESPONSE_MODE_QUERY,
+    RESPONSE_MODE_FRAGMENT,
+    RESPONSE_MODE_FORM_POST,
+];
+
+// A response type is a set of space-delimited values, so `id_token code` and
+// `code id_token` are the same one.
+pub fn normalize_response_type(response_type: &str) -> String {
+    let mut values: Vec<&str> = response_type
+        .split(' ')
+        .filter(|value| !value.is_empty())
+        .collect();
+    values.sort_unstable();
+    values.dedup();
+
+    values.join(" ")
+}
+
+pub fn response_type_includes(response_type: &str, value: &str) -> bool {
+    response_type.split(' ').any(|item| item == value)
+}
+
+// `application.response_types` holds the JSON list of response types the
+// client may use. Applications that never set it keep to the code flow.
+pub fn allowed_response_types(app: &application::Model) -> Vec<String> {
+    app.response_types
+        .as_deref()
+        .and_then(|response_types| serde_json::from_str(response_types).ok())
+        .unwrap_or_else(|| vec![RESPONSE_TYPE_CODE.to_string()])
+}
+
+pub fn response_type_allowed(app: &application::Model, response_type: &str) -> bool {
+    allowed_response_types(app)
+        .iter()
+        .any(|allowed| normalize_response_type(allowed) == response_type)
+}
+
+// Tokens never travel in the query string, where they would end up in logs
+// and `Referer` headers.
+pub fn default_response_mode(response_type: &str) -> &'static str {
+    match response_type {
+        RESPONSE_TYPE_CODE => RESPONSE_MODE_QUERY,
+        _ => RESPONSE_MODE_FRAGMENT,
+    }
+}
+
+pub fn is_valid_response_mode(response_type: &str, response_mode: &str) -> bool {
+    match response_mode {
+        RESPONSE_MODE_QUERY => response_type == RESPONSE_TYPE_CODE,
+        RESPONSE_MODE_FRAGMENT | RESPONSE_MODE_FORM_POST => true,
+        _ => false,
+    }
+}
+
+// A page that POSTs the response parameters to the redirect URI as soon as it loads.
+fn form_post_page(redirect_uri: &url::Url, params: &[(&str, String)]) -> Html<String> {
+    let inputs: String = params
+        .iter()
+        .map(|(name, value)| {
+            format!(
+                r#"<input type="hidden" name="{}" value="{}">"#,
+                escape_html(name),
+                escape_html(value)
+            )
+        })
+        .collect();
+
+    Html(format!(
+        r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>Submitting</title>
+</head>
+<body onload="document.forms[0].submit()">
+<form method="post" action="{action}">
+{inputs}
+<noscript><button type="submit">Continue</button></noscript>
+</form>
+</body>
+</html>
+"#,
+        action = escape_html(redirect_uri.as_str())
+    ))
+}
+
+// Deliver the authorization response to the client in the requested mode.
+pub fn authorization_response(
+    mut redirect_uri: url::Url,
+    response_mode: &str,
+    params: &[(&str, String)],
+) -> Response {
+    match response_mode {
+        RESPONSE_MODE_FORM_POST => form_post_page(&redirect_uri, params).into_response(),
+        RESPONSE_MODE_FRAGMENT => {
+            let fragment = url::form_urlencoded::Serializer::new(String::new())
+                .extend_pairs(params)
+                .finish();
+            redirect_uri.set_fragment(Some(&fragment));
+            Redirect::to(redirect_uri.as_str()).into_response()
+        }
+        _ => {
+            redirect_uri.query_pairs_mut().extend_pairs(params);
+            Redirect::to(redirect_uri.as_str()).into_response()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{default_response_mode, is_valid_response_mode, normalize_response_type};
+
+    #[test]
+    fn tokens_stay_out_of_the_query() {
+        assert_eq!(normalize_response_type("id_token  code"), "code id_token");
+        assert_eq!(default_response_mode("code"), "query");
+        assert_eq!(default_response_mode("code id_token"), "fragment");
+        assert!(!is_valid_response_mode("code token", "query"));
+        assert!(is_valid_response_mode("id_token", "form_post"));
+        assert!(!is_valid_response_mode("code", "web_message"));
+    }
+}
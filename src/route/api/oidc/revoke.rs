@@ -0,0 +1,108 @@
+use axum::{
+    extract::{Extension, Form},
+    http::{HeaderMap, StatusCode},
+    response::IntoResponse,
+};
+use sea_orm::{
+    sea_query::Expr, ColumnTrait, Condition, ConnectionTrait, DatabaseConnection, EntityTrait,
+    QueryFilter, TransactionTrait,
+};
+use serde::Deserialize;
+
+use entity::token;
+
+use super::{
+    access_token::access_token_lookup_key,
+    client_auth::{authenticate_client, ClientAuthParams},
+    keys::OidcKeys,
+};
+use crate::{
+    error::{AppError, OAuthError, ServiceError},
+    model::token_denylist::{delete_tokens, deny_access_tokens},
+};
+
+#[derive(Debug, Deserialize)]
+pub struct RevokeRequest {
+    token: String,
+    token_type_hint: Option<String>,
+    #[serde(flatten)]
+    client: ClientAuthParams,
+}
+
+async fn find_by_access_token<C: ConnectionTrait>(
+    db: &C,
+    value: &str,
+) -> Result<Option<token::Model>, AppError> {
+    Ok(token::Entity::find()
+        .filter(token::Column::AccessToken.eq(value))
+        .one(db)
+        .await?)
+}
+
+async fn find_by_refresh_token<C: ConnectionTrait>(
+    db: &C,
+    value: &str,
+) -> Result<Option<token::Model>, AppError> {
+    Ok(token::Entity::find()
+        .filter(token::Column::RefreshToken.eq(value))
+        .one(db)
+        .await?)
+}
+
+pub async fn handler(
+    Extension(conn): Extension<DatabaseConnection>,
+    Extension(oidc_keys): Extension<OidcKeys>,
+    Extension(redis_client): Extension<redis::Client>,
+    headers: HeaderMap,
+    Form(form): Form<RevokeRequest>,
+) -> Result<impl IntoResponse, OAuthError> {
+    let app = authenticate_client(&conn, &redis_client, &headers, &form.client).await?;
+    let access_token = access_token_lookup_key(&oidc_keys, &form.token);
+
+    let txn = conn.begin().await?;
+
+    // Tokens are looked up as refresh tokens first only when the client says so.
+    let (found, is_refresh_token) = match form.token_type_hint.as_deref() {
+        Some("refresh_token") => match find_by_refresh_token(&txn, &form.token).await? {
+            Some(token) => (Some(token), true),
+            None => (find_by_access_token(&txn, &access_token).await?, false),
+        },
+        _ => match find_by_access_token(&txn, &access_token).await? {
+            Some(token) => (Some(token), false),
+            None => (find_by_refresh_token(&txn, &form.token).await?, true),
+        },
+    };
+
+    // Unknown tokens are reported as revoked, as RFC 7009 requires.
+    let Some(found) = found else {
+        return Ok(StatusCode::OK);
+    };
+
+    if found.application_id != app.id {
+        return Err(ServiceError::UnauthorizedClient.into());
+    }
+
+    if is_refresh_token {
+        // Dropping the family takes every access token derived from the grant with it.
+        delete_tokens(
+            &txn,
+            Condition::all().add(token::Column::FamilyId.eq(found.family_id)),
+        )
+        .await?;
+    } else {
+        // The refresh token issued alongside stays usable.
+        deny_access_tokens(&txn, std::slice::from_ref(&found)).await?;
+        token::Entity::update_many()
+            .col_expr(
+                token::Column::AccessTokenRevokedAt,
+                Expr::value(chrono::Utc::now().naive_utc()),
+            )
+            .filter(token::Column::Id.eq(found.id))
+            .exec(&txn)
+            .await?;
+    }
+
+    txn.commit().await?;
+
+    Ok(StatusCode::OK)
+}
@@ -0,0 +1,40 @@
+use std::collections::BTreeSet;
+
+pub type Scopes = BTreeSet<String>;
+
+pub fn parse_scope(scope: &str) -> Scopes {
+    scope
+        .split_whitespace()
+        .map(|item| item.to_string())
+        .collect()
+}
+
+pub fn to_scope_string(scopes: &Scopes) -> String {
+    scopes.iter().cloned().collect::<Vec<_>>().join(" ")
+}
+
+// Scopes are persisted as JSON arrays, like the other list columns
+pub fn scopes_to_json(scopes: &Scopes) -> String {
+    serde_json::to_string(scopes).unwrap()
+}
+
+pub fn scopes_from_json(json: &str) -> Scopes {
+    serde_json::from_str(json).unwrap_or_else(|_| parse_scope(json))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_scope, scopes_from_json, scopes_to_json, to_scope_string};
+
+    #[test]
+    fn scope_round_trips_through_json() {
+        let scopes = parse_scope("openid  email profile openid");
+
+        assert_eq!(to_scope_string(&scopes), "email openid profile");
+        assert_eq!(scopes_from_json(&scopes_to_json(&scopes)), scopes);
+        assert_eq!(
+            scopes_from_json("openid email"),
+            parse_scope("email openid")
+        );
+    }
+}
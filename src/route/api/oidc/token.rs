@@ -1,153 +1,466 @@
 use axum::{
     extract::{Extension, Form},
+    http::HeaderMap,
     response::{IntoResponse, Json},
 };
-use jsonwebtoken::{encode, Algorithm, EncodingKey, Header};
 use sea_orm::{
-    ActiveModelTrait, ColumnTrait, DbErr, EntityTrait, ModelTrait, QueryFilter, Set,
+    sea_query::Expr, ColumnTrait, Condition, DatabaseConnection, EntityTrait, QueryFilter,
     TransactionTrait,
 };
-use serde::{Deserialize, Serialize};
-use uuid::Uuid;
+use serde::Deserialize;
 
-use entity::{application, authorization_code, token, user};
+use entity::{application, authorization_code, token};
 
-use super::well_known::OidcKeys;
+use super::{
+    client_auth::{authenticate_client, grant_type_allowed, is_public_client, ClientAuthParams},
+    device::{poll_device_authorization, DEVICE_CODE_GRANT_TYPE},
+    dpop::{verify_dpop_proof, DpopRequest},
+    issue::{issue_tokens, IssueTokenParams, TokenResponse, OFFLINE_ACCESS_SCOPE},
+    keys::OidcKeys,
+    pkce::verify_code_verifier,
+    scope::{parse_scope, scopes_from_json},
+    token_exchange::{
+        find_active_access_token, token_subject, Actor, ACCESS_TOKEN_TYPE,
+        TOKEN_EXCHANGE_GRANT_TYPE,
+    },
+};
 use crate::{
-    constants::PARSED_FRONTEND_URL,
-    error::{AppError, ServiceError},
+    error::{AppError, OAuthError, ServiceError},
+    model::{token_denylist::delete_tokens, token_exchange_policy::TokenExchangePolicyModel},
 };
-use entity::application_secret;
+
+const TOKEN_ENDPOINT_PATH: &str = "api/oidc/token";
+
+pub const GRANT_TYPES: [&str; 5] = [
+    "authorization_code",
+    "refresh_token",
+    "client_credentials",
+    DEVICE_CODE_GRANT_TYPE,
+    TOKEN_EXCHANGE_GRANT_TYPE,
+];
 
 #[derive(Debug, Deserialize)]
 pub struct TokenRequest {
     grant_type: String,
-    code: String,
-    redirect_uri: String,
-    client_id: String,
-    client_secret: String,
-}
-
-#[derive(Debug, Serialize)]
-pub struct TokenResponse {
-    access_token: String,
-    id_token: String,
-    token_type: String,
-    expires_in: i64,
-    refresh_token: String,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-pub struct Claims {
-    iss: String,
-    sub: String,
-    aud: String,
-    exp: usize,
-    iat: usize,
+    code: Option<String>,
+    redirect_uri: Option<String>,
+    refresh_token: Option<String>,
+    scope: Option<String>,
+    code_verifier: Option<String>,
+    device_code: Option<String>,
+    subject_token: Option<String>,
+    subject_token_type: Option<String>,
+    actor_token: Option<String>,
+    actor_token_type: Option<String>,
+    requested_token_type: Option<String>,
+    audience: Option<String>,
+    #[serde(flatten)]
+    client: ClientAuthParams,
 }
 
 pub async fn handler(
-    Extension(conn): Extension<sea_orm::DatabaseConnection>,
+    Extension(conn): Extension<DatabaseConnection>,
     Extension(oidc_keys): Extension<OidcKeys>,
+    Extension(redis_client): Extension<redis::Client>,
+    headers: HeaderMap,
     Form(form): Form<TokenRequest>,
-) -> Result<impl IntoResponse, AppError> {
-    if form.grant_type != "authorization_code" {
-        return Err(AppError::ServiceError(ServiceError::InvalidGrant));
+) -> Result<impl IntoResponse, OAuthError> {
+    if !GRANT_TYPES.contains(&form.grant_type.as_str()) {
+        return Err(ServiceError::UnsupportedGrantType.into());
+    }
+
+    let app = authenticate_client(&conn, &redis_client, &headers, &form.client).await?;
+    if !grant_type_allowed(&app, &form.grant_type) {
+        return Err(ServiceError::UnauthorizedClient.into());
     }
 
+    // Tokens requested with a DPoP proof are bound to the key that signed it
+    let dpop_jkt = verify_dpop_proof(
+        &redis_client,
+        &headers,
+        DpopRequest {
+            method: "POST",
+            path: TOKEN_ENDPOINT_PATH,
+            access_token: None,
+        },
+    )
+    .await?;
+
+    let response = match form.grant_type.as_str() {
+        "authorization_code" => {
+            authorization_code_grant(&conn, &oidc_keys, app, form, dpop_jkt).await?
+        }
+        "refresh_token" => refresh_token_grant(&conn, &oidc_keys, app, form, dpop_jkt).await?,
+        DEVICE_CODE_GRANT_TYPE => {
+            device_code_grant(&conn, &oidc_keys, &redis_client, app, form, dpop_jkt).await?
+        }
+        TOKEN_EXCHANGE_GRANT_TYPE => {
+            token_exchange_grant(&conn, &oidc_keys, app, form, dpop_jkt).await?
+        }
+        _ => client_credentials_grant(&conn, &oidc_keys, app, form, dpop_jkt).await?,
+    };
+
+    Ok(Json(response))
+}
+
+async fn authorization_code_grant(
+    conn: &DatabaseConnection,
+    oidc_keys: &OidcKeys,
+    app: application::Model,
+    form: TokenRequest,
+    dpop_jkt: Option<String>,
+) -> Result<TokenResponse, AppError> {
+    let code = form
+        .code
+        .ok_or(AppError::ServiceError(ServiceError::InvalidGrant))?;
+
     let txn = conn.begin().await?;
 
     // Fetch and validate the authorization code from the database.
     let auth_code = authorization_code::Entity::find()
-        .filter(authorization_code::Column::Code.eq(form.code.clone()))
+        .filter(authorization_code::Column::Code.eq(code))
         .one(&txn)
         .await?
-        .ok_or_else(|| AppError::ServiceError(ServiceError::InvalidGrant))?;
+        .ok_or(AppError::ServiceError(ServiceError::InvalidGrant))?;
 
     if auth_code.expires_at < chrono::Utc::now().naive_utc() {
         return Err(AppError::ServiceError(ServiceError::InvalidGrant));
     }
 
-    let app = auth_code
-        .find_related(application::Entity)
-        .one(&txn)
-        .await?
-        .ok_or_else(|| AppError::ServiceError(ServiceError::InvalidClient))?;
-
-    if app.id != form.client_id {
+    if auth_code.application_id != app.id {
         return Err(AppError::ServiceError(ServiceError::InvalidClient));
     }
 
-    if auth_code.redirect_uri != form.redirect_uri {
+    if form.redirect_uri.as_deref() != Some(auth_code.redirect_uri.as_str()) {
+        return Err(AppError::ServiceError(ServiceError::InvalidGrant));
+    }
+
+    // A code issued with a PKCE challenge can only be redeemed with its verifier.
+    if let Some(code_challenge) = &auth_code.code_challenge {
+        let code_challenge_method = auth_code
+            .code_challenge_method
+            .as_deref()
+            .unwrap_or("plain");
+        let verified = form
+            .code_verifier
+            .as_deref()
+            .map_or(false, |code_verifier| {
+                verify_code_verifier(code_verifier, code_challenge, code_challenge_method)
+            });
+
+        if !verified {
+            return Err(AppError::ServiceError(ServiceError::InvalidGrant));
+        }
+    } else if is_public_client(&app) {
+        // Public clients have nothing but the verifier to prove they requested the code
         return Err(AppError::ServiceError(ServiceError::InvalidGrant));
     }
 
     // Delete the authorization code as it's for single-use.
-    let _code_to_delete = auth_code.code.clone();
     authorization_code::Entity::delete_by_id(auth_code.code)
         .exec(&txn)
         .await?;
 
-    // Verify the client secret.
-    let app_secret = app
-        .find_related(application_secret::Entity)
+    let response = issue_tokens(
+        &txn,
+        oidc_keys,
+        IssueTokenParams {
+            app: &app,
+            user_id: Some(auth_code.user_id),
+            scopes: scopes_from_json(&auth_code.scopes),
+            auth_time: auth_code.auth_time,
+            nonce: auth_code.nonce,
+            family_id: None,
+            sid: auth_code.sid,
+            audience: None,
+            act: None,
+            dpop_jkt,
+        },
+    )
+    .await?;
+
+    txn.commit().await?;
+
+    Ok(response)
+}
+
+async fn refresh_token_grant(
+    conn: &DatabaseConnection,
+    oidc_keys: &OidcKeys,
+    app: application::Model,
+    form: TokenRequest,
+    dpop_jkt: Option<String>,
+) -> Result<TokenResponse, AppError> {
+    let refresh_token = form
+        .refresh_token
+        .ok_or(AppError::ServiceError(ServiceError::InvalidGrant))?;
+
+    let txn = conn.begin().await?;
+
+    let current = token::Entity::find()
+        .filter(token::Column::RefreshToken.eq(refresh_token))
         .one(&txn)
         .await?
-        .ok_or_else(|| AppError::ServiceError(ServiceError::InvalidClient))?;
+        .ok_or(AppError::ServiceError(ServiceError::InvalidGrant))?;
 
-    if form.client_secret != app_secret.secret {
-        return Err(AppError::ServiceError(ServiceError::InvalidClient));
+    if current.application_id != app.id {
+        return Err(AppError::ServiceError(ServiceError::InvalidGrant));
     }
 
-    // Ensure the user exists.
-    let user = user::Entity::find_by_id(auth_code.user_id.clone())
-        .one(&txn)
-        .await?
-        .ok_or_else(|| AppError::from(DbErr::RecordNotFound("User not found".to_string())))?;
-
-    // Generate new access and refresh tokens.
-    let access_token = Uuid::new_v4().to_string();
-    let refresh_token = Uuid::new_v4().to_string();
-    let expires_in = 3600; // 1 hour in seconds.
-    let now = chrono::Utc::now();
-    let expires_at = now + chrono::Duration::seconds(expires_in);
-
-    // Create and save a new token to the database.
-    let new_token = token::ActiveModel {
-        access_token: Set(access_token.clone()),
-        refresh_token: Set(refresh_token.clone()),
-        user_id: Set(auth_code.user_id),
-        application_id: Set(app.id.clone()),
-        scopes: Set(auth_code.scopes),
-        expires_at: Set(expires_at.naive_utc()),
-        ..Default::default()
+    let now = chrono::Utc::now().naive_utc();
+
+    // Mark the refresh token as used. Only the first caller flips the flag, so a
+    // token that has already been rotated (or is being rotated concurrently) is
+    // treated as stolen and the whole family is revoked.
+    let rotated = token::Entity::update_many()
+        .col_expr(token::Column::RefreshTokenUsedAt, Expr::value(now))
+        .filter(token::Column::Id.eq(current.id))
+        .filter(token::Column::RefreshTokenUsedAt.is_null())
+        .exec(&txn)
+        .await?;
+
+    if rotated.rows_affected == 0 {
+        delete_tokens(
+            &txn,
+            Condition::all().add(token::Column::FamilyId.eq(current.family_id)),
+        )
+        .await?;
+        txn.commit().await?;
+
+        return Err(AppError::ServiceError(ServiceError::InvalidGrant));
+    }
+
+    // A bound refresh token can only be used with the key it is bound to, and
+    // the tokens replacing it stay bound to that key
+    if current.dpop_jkt.is_some() && current.dpop_jkt != dpop_jkt {
+        return Err(AppError::ServiceError(ServiceError::InvalidDpopProof));
+    }
+
+    if current
+        .refresh_expires_at
+        .map_or(true, |refresh_expires_at| refresh_expires_at < now)
+    {
+        return Err(AppError::ServiceError(ServiceError::InvalidGrant));
+    }
+
+    // The client may narrow the scopes of the new tokens, but never widen them.
+    let granted_scopes = scopes_from_json(&current.scopes);
+    let scopes = match form.scope {
+        Some(scope) => {
+            let requested_scopes = parse_scope(&scope);
+            if !granted_scopes.is_superset(&requested_scopes) {
+                return Err(AppError::ServiceError(ServiceError::InvalidScope));
+            }
+            requested_scopes
+        }
+        None => granted_scopes,
     };
-    new_token.insert(&txn).await?;
-
-    let claims = Claims {
-        iss: PARSED_FRONTEND_URL.to_string(),
-        sub: user.id.to_string(),
-        aud: app.id,
-        exp: expires_at.timestamp() as usize,
-        iat: now.timestamp() as usize,
+
+    let response = issue_tokens(
+        &txn,
+        oidc_keys,
+        IssueTokenParams {
+            app: &app,
+            user_id: current.user_id,
+            scopes,
+            auth_time: current.auth_time,
+            nonce: None,
+            family_id: Some(current.family_id),
+            sid: current.sid,
+            audience: None,
+            act: None,
+            dpop_jkt: current.dpop_jkt.or(dpop_jkt),
+        },
+    )
+    .await?;
+
+    txn.commit().await?;
+
+    Ok(response)
+}
+
+async fn client_credentials_grant(
+    conn: &DatabaseConnection,
+    oidc_keys: &OidcKeys,
+    app: application::Model,
+    form: TokenRequest,
+    dpop_jkt: Option<String>,
+) -> Result<TokenResponse, AppError> {
+    if is_public_client(&app) {
+        return Err(AppError::ServiceError(ServiceError::UnauthorizedClient));
+    }
+
+    // Without an explicit scope the token gets everything the application is allowed.
+    let allowed_scopes = app
+        .allowed_scopes
+        .as_deref()
+        .map(scopes_from_json)
+        .unwrap_or_default();
+    let scopes = match form.scope {
+        Some(scope) => {
+            let requested_scopes = parse_scope(&scope);
+            if !allowed_scopes.is_superset(&requested_scopes) {
+                return Err(AppError::ServiceError(ServiceError::InvalidScope));
+            }
+            requested_scopes
+        }
+        None => allowed_scopes,
     };
 
-    let id_token = encode(
-        &Header::new(Algorithm::RS256),
-        &claims,
-        &EncodingKey::from_rsa_pem(&oidc_keys.private_key.private_key_to_pem().unwrap()).unwrap(),
-    )?;
+    issue_tokens(
+        conn,
+        oidc_keys,
+        IssueTokenParams {
+            app: &app,
+            user_id: None,
+            scopes,
+            auth_time: None,
+            nonce: None,
+            family_id: None,
+            sid: None,
+            audience: None,
+            act: None,
+            dpop_jkt,
+        },
+    )
+    .await
+}
+
+async fn device_code_grant(
+    conn: &DatabaseConnection,
+    oidc_keys: &OidcKeys,
+    redis_client: &redis::Client,
+    app: application::Model,
+    form: TokenRequest,
+    dpop_jkt: Option<String>,
+) -> Result<TokenResponse, AppError> {
+    let device_code = form
+        .device_code
+        .ok_or(AppError::ServiceError(ServiceError::InvalidGrant))?;
+    let authorization = poll_device_authorization(redis_client, &app.id, &device_code).await?;
 
+    let txn = conn.begin().await?;
+    let response = issue_tokens(
+        &txn,
+        oidc_keys,
+        IssueTokenParams {
+            app: &app,
+            user_id: authorization.user_id,
+            scopes: authorization.scopes,
+            auth_time: authorization
+                .auth_time
+                .and_then(|auth_time| chrono::DateTime::from_timestamp(auth_time, 0))
+                .map(|auth_time| auth_time.naive_utc()),
+            nonce: None,
+            family_id: None,
+            sid: authorization.sid,
+            audience: None,
+            act: None,
+            dpop_jkt,
+        },
+    )
+    .await?;
     txn.commit().await?;
 
-    // Construct the JSON response.
-    let response = TokenResponse {
-        access_token,
-        id_token,
-        token_type: "Bearer".to_string(),
-        expires_in,
-        refresh_token,
+    Ok(response)
+}
+
+// RFC 8693: trade a user's access token for one aimed at another service,
+// recording the party acting on the user's behalf in `act`.
+async fn token_exchange_grant(
+    conn: &DatabaseConnection,
+    oidc_keys: &OidcKeys,
+    app: application::Model,
+    form: TokenRequest,
+    dpop_jkt: Option<String>,
+) -> Result<TokenResponse, AppError> {
+    if is_public_client(&app) {
+        return Err(AppError::ServiceError(ServiceError::UnauthorizedClient));
+    }
+
+    let subject_token = form
+        .subject_token
+        .ok_or(AppError::ServiceError(ServiceError::InvalidRequest))?;
+    if form.subject_token_type.as_deref() != Some(ACCESS_TOKEN_TYPE)
+        || (form.actor_token.is_some()
+            && form.actor_token_type.as_deref() != Some(ACCESS_TOKEN_TYPE))
+        || form
+            .requested_token_type
+            .as_deref()
+            .map_or(false, |requested| requested != ACCESS_TOKEN_TYPE)
+    {
+        return Err(AppError::ServiceError(ServiceError::InvalidRequest));
+    }
+
+    // The application needs a policy for every audience it exchanges tokens for
+    let audience = form
+        .audience
+        .ok_or(AppError::ServiceError(ServiceError::InvalidRequest))?;
+    TokenExchangePolicyModel::new(conn)
+        .find_one_policy(&app.id, &audience)
+        .await?
+        .ok_or(AppError::ServiceError(ServiceError::InvalidTarget))?;
+
+    // Only tokens issued to the client, or exchanged for it, can be passed on
+    let subject = find_active_access_token(conn, oidc_keys, &subject_token)
+        .await?
+        .filter(|subject| {
+            subject.user_id.is_some()
+                && (subject.application_id == app.id
+                    || subject.audience.as_deref() == Some(app.id.as_str()))
+        })
+        .ok_or(AppError::ServiceError(ServiceError::InvalidRequest))?;
+
+    // Without an actor token the client itself acts for the user
+    let actor_sub = match &form.actor_token {
+        Some(actor_token) => token_subject(
+            &find_active_access_token(conn, oidc_keys, actor_token)
+                .await?
+                .ok_or(AppError::ServiceError(ServiceError::InvalidRequest))?,
+        ),
+        None => app.id.clone(),
+    };
+    let act = Actor {
+        sub: actor_sub,
+        act: subject
+            .act
+            .as_deref()
+            .and_then(Actor::from_json)
+            .map(Box::new),
     };
 
-    Ok(Json(response))
+    // The new token may narrow the subject token's scopes, but never widen them.
+    let granted_scopes = scopes_from_json(&subject.scopes);
+    let mut scopes = match form.scope {
+        Some(scope) => {
+            let requested_scopes = parse_scope(&scope);
+            if !granted_scopes.is_superset(&requested_scopes) {
+                return Err(AppError::ServiceError(ServiceError::InvalidScope));
+            }
+            requested_scopes
+        }
+        None => granted_scopes,
+    };
+    scopes.remove(OFFLINE_ACCESS_SCOPE);
+
+    let mut response = issue_tokens(
+        conn,
+        oidc_keys,
+        IssueTokenParams {
+            app: &app,
+            user_id: subject.user_id,
+            scopes,
+            auth_time: subject.auth_time,
+            nonce: None,
+            family_id: None,
+            sid: subject.sid,
+            audience: Some(audience),
+            act: Some(act),
+            dpop_jkt,
+        },
+    )
+    .await?;
+    response.issued_token_type = Some(ACCESS_TOKEN_TYPE.to_string());
+
+    Ok(response)
 }
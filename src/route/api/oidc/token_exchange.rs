@@ -0,0 +1,72 @@
+use sea_orm::{ColumnTrait, ConnectionTrait, DbErr, EntityTrait, QueryFilter};
+use serde::{Deserialize, Serialize};
+
+use entity::token;
+
+use super::{access_token::access_token_lookup_key, keys::OidcKeys};
+
+pub const TOKEN_EXCHANGE_GRANT_TYPE: &str = "urn:ietf:params:oauth:grant-type:token-exchange";
+// Our own access tokens are the only kind exchanged and issued
+pub const ACCESS_TOKEN_TYPE: &str = "urn:ietf:params:oauth:token-type:access_token";
+
+// The `act` claim of RFC 8693. Earlier actors of a delegation chain nest inside.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Actor {
+    pub sub: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub act: Option<Box<Actor>>,
+}
+
+impl Actor {
+    pub fn from_json(value: &str) -> Option<Self> {
+        serde_json::from_str(value).ok()
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+}
+
+// Whom a token speaks for: its user, or the client itself for machine tokens
+pub fn token_subject(token: &token::Model) -> String {
+    token.user_id.map_or_else(
+        || token.application_id.clone(),
+        |user_id| user_id.to_string(),
+    )
+}
+
+// An access token that has neither expired nor been revoked
+pub async fn find_active_access_token<C: ConnectionTrait>(
+    db: &C,
+    oidc_keys: &OidcKeys,
+    presented: &str,
+) -> Result<Option<token::Model>, DbErr> {
+    token::Entity::find()
+        .filter(token::Column::AccessToken.eq(access_token_lookup_key(oidc_keys, presented)))
+        .filter(token::Column::ExpiresAt.gt(chrono::Utc::now().naive_utc()))
+        .filter(token::Column::AccessTokenRevokedAt.is_null())
+        .one(db)
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Actor;
+
+    #[test]
+    fn earlier_actors_nest() {
+        let actor = Actor {
+            sub: "gateway".to_string(),
+            act: Some(Box::new(Actor {
+                sub: "frontend".to_string(),
+                act: None,
+            })),
+        };
+
+        assert_eq!(
+            actor.to_json(),
+            r#"{"sub":"gateway","act":{"sub":"frontend"}}"#
+        );
+        assert_eq!(Actor::from_json(&actor.to_json()), Some(actor));
+    }
+}
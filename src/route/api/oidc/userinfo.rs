@@ -1,52 +1,162 @@
 use axum::{
     extract::Extension,
-    http::HeaderMap,
-    response::{IntoResponse, Json},
+    http::{
+        header::{ACCEPT, AUTHORIZATION, CONTENT_TYPE},
+        HeaderMap, Method,
+    },
+    response::{IntoResponse, Json, Response},
 };
-use sea_orm::{ColumnTrait, EntityTrait, ModelTrait, QueryFilter};
-use serde_json::json;
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
+use serde::Serialize;
 
-use entity::{token, user};
+use entity::{application, image, token, user};
 
-use crate::{error::AppError, error::ServiceError};
+use super::{
+    access_token::access_token_lookup_key,
+    claims::UserClaims,
+    dpop::{verify_dpop_proof, DpopRequest, DPOP_TOKEN_TYPE},
+    keys::{signing_algorithm, OidcKeys},
+    scope::scopes_from_json,
+};
+use crate::{
+    constants::PARSED_FRONTEND_URL, error::AppError, error::OAuthError, error::ServiceError,
+};
+
+const JWT_CONTENT_TYPE: &str = "application/jwt";
+
+#[derive(Debug, Serialize)]
+struct UserinfoClaims {
+    sub: String,
+    // Only present in signed responses
+    #[serde(skip_serializing_if = "Option::is_none")]
+    iss: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    aud: Option<String>,
+    #[serde(flatten)]
+    user_claims: UserClaims,
+}
+
+const USERINFO_ENDPOINT_PATH: &str = "api/oidc/userinfo";
+
+// The authorization scheme, `Bearer` or `DPoP`, and the access token
+fn presented_access_token(headers: &HeaderMap) -> Option<(&str, &str)> {
+    headers
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split_once(' '))
+}
 
 pub async fn handler(
     Extension(conn): Extension<sea_orm::DatabaseConnection>,
+    Extension(oidc_keys): Extension<OidcKeys>,
+    Extension(redis_client): Extension<redis::Client>,
+    method: Method,
     headers: HeaderMap,
-) -> Result<impl IntoResponse, AppError> {
-    let auth_header = headers
-        .get("Authorization")
-        .and_then(|value| value.to_str().ok())
-        .ok_or_else(|| AppError::ServiceError(ServiceError::InvalidToken))?;
+) -> Result<Response, OAuthError> {
+    let dpop = presented_access_token(&headers).map_or(false, |(scheme, _)| {
+        scheme.eq_ignore_ascii_case(DPOP_TOKEN_TYPE)
+    });
 
-    if !auth_header.starts_with("Bearer ") {
+    userinfo(&conn, &oidc_keys, &redis_client, &method, &headers)
+        .await
+        .map_err(|err| match dpop {
+            true => OAuthError::from(err).dpop_challenge(),
+            false => err.into(),
+        })
+}
+
+async fn userinfo(
+    conn: &sea_orm::DatabaseConnection,
+    oidc_keys: &OidcKeys,
+    redis_client: &redis::Client,
+    method: &Method,
+    headers: &HeaderMap,
+) -> Result<Response, AppError> {
+    let (scheme, presented) = presented_access_token(headers)
+        .ok_or_else(|| AppError::ServiceError(ServiceError::InvalidToken))?;
+    let dpop = scheme.eq_ignore_ascii_case(DPOP_TOKEN_TYPE);
+    if !dpop && !scheme.eq_ignore_ascii_case("Bearer") {
         return Err(AppError::ServiceError(ServiceError::InvalidToken));
     }
 
-    let access_token = &auth_header[7..];
+    let access_token = access_token_lookup_key(oidc_keys, presented);
 
     let token = token::Entity::find()
         .filter(token::Column::AccessToken.eq(access_token))
-        .one(&conn)
+        .one(conn)
         .await?
         .ok_or_else(|| AppError::ServiceError(ServiceError::InvalidToken))?;
 
-    if token.expires_at < chrono::Utc::now().naive_utc() {
+    if token.expires_at < chrono::Utc::now().naive_utc() || token.access_token_revoked_at.is_some()
+    {
         return Err(AppError::ServiceError(ServiceError::InvalidToken));
     }
 
-    let user = token
-        .find_related(user::Entity)
-        .one(&conn)
+    // Bound tokens are only accepted with a proof from the key they are bound to,
+    // and plain bearer tokens are never sent with the DPoP scheme
+    if dpop != token.dpop_jkt.is_some() {
+        return Err(AppError::ServiceError(ServiceError::InvalidToken));
+    }
+    if dpop {
+        let jkt = verify_dpop_proof(
+            redis_client,
+            headers,
+            DpopRequest {
+                method: method.as_str(),
+                path: USERINFO_ENDPOINT_PATH,
+                access_token: Some(presented),
+            },
+        )
+        .await?;
+        if jkt != token.dpop_jkt {
+            return Err(AppError::ServiceError(ServiceError::InvalidDpopProof));
+        }
+    }
+
+    // Machine tokens from client_credentials have no user to describe
+    let user_id = token
+        .user_id
+        .ok_or_else(|| AppError::ServiceError(ServiceError::InvalidToken))?;
+
+    let (user, face) = user::Entity::find_by_id(user_id)
+        .find_also_related(image::Entity)
+        .one(conn)
         .await?
         .ok_or_else(|| AppError::ServiceError(ServiceError::NotFound))?;
 
-    let claims = json!({
-        "sub": user.id,
-        "name": user.username,
-        "email": user.email,
-        "picture": user.face_id,
-    });
+    let app = application::Entity::find_by_id(token.application_id.clone())
+        .one(conn)
+        .await?
+        .ok_or_else(|| AppError::ServiceError(ServiceError::InvalidToken))?;
+
+    let scopes = scopes_from_json(&token.scopes);
+    let mut claims = UserinfoClaims {
+        sub: user.id.to_string(),
+        iss: None,
+        aud: None,
+        user_claims: UserClaims::new(&user, face.as_ref(), &scopes),
+    };
+
+    // Clients get a signed response when they registered an algorithm for it or
+    // ask for one through the Accept header.
+    let wants_jwt = headers
+        .get(ACCEPT)
+        .and_then(|value| value.to_str().ok())
+        .map_or(false, |accept| accept.contains(JWT_CONTENT_TYPE));
+
+    if app.userinfo_signed_response_alg.is_none() && !wants_jwt {
+        return Ok(Json(claims).into_response());
+    }
+
+    claims.iss = Some(PARSED_FRONTEND_URL.to_string());
+    claims.aud = Some(app.id.clone());
+
+    let alg = signing_algorithm(
+        app.userinfo_signed_response_alg
+            .as_deref()
+            .or(app.id_token_signed_response_alg.as_deref()),
+    );
+    let jwt = oidc_keys.sign(alg, &claims)?;
 
-    Ok(Json(claims))
+    Ok(([(CONTENT_TYPE, JWT_CONTENT_TYPE)], jwt).into_response())
 }
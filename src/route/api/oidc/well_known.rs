@@ -1,61 +1,73 @@
 use axum::extract::Extension;
 use axum::Json;
-use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
-use openssl::pkey::{PKey, Private};
-use openssl::rsa::Rsa;
 use serde_json::{json, Value};
 
-use crate::constants::PARSED_FRONTEND_URL;
-
-#[derive(Clone)]
-pub struct OidcKeys {
-    pub private_key: Rsa<Private>,
-    #[allow(dead_code)]
-    pub public_key_pem: String,
-}
-
-impl OidcKeys {
-    pub fn new() -> Self {
-        let rsa = Rsa::generate(2048).unwrap();
-        let public_key_pem = String::from_utf8(rsa.public_key_to_pem().unwrap()).unwrap();
-        Self {
-            private_key: rsa,
-            public_key_pem,
-        }
-    }
-}
+use super::{
+    authorize::PROMPT_VALUES,
+    client_auth::{CLIENT_ASSERTION_SIGNING_ALGORITHMS, TOKEN_ENDPOINT_AUTH_METHODS},
+    dpop::DPOP_SIGNING_ALGORITHMS,
+    keys::{OidcKeys, SIGNING_ALGORITHMS},
+    pkce::CODE_CHALLENGE_METHODS,
+    response::{RESPONSE_MODES, RESPONSE_TYPES},
+    token::GRANT_TYPES,
+};
+use crate::constants::{AUTH_METHOD_NONE, PARSED_FRONTEND_URL};
 
 pub async fn jwks_handler(Extension(oidc_keys): Extension<OidcKeys>) -> Json<Value> {
-    let pkey = PKey::from_rsa(oidc_keys.private_key.clone()).unwrap();
-    let rsa = pkey.rsa().unwrap();
-    let n = rsa.n();
-    let e = rsa.e();
-
-    let jwk = json!({
-        "kty": "RSA",
-        "use": "sig",
-        "alg": "RS256",
-        "kid": "1", // In a real scenario, this would be a dynamic key ID
-        "n": URL_SAFE_NO_PAD.encode(n.to_vec()),
-        "e": URL_SAFE_NO_PAD.encode(e.to_vec()),
-    });
-
-    Json(json!({
-        "keys": [jwk]
-    }))
+    Json(oidc_keys.jwks())
 }
 
 pub async fn openid_configuration_handler() -> Json<Value> {
     let issuer = PARSED_FRONTEND_URL.to_string();
+    // Public clients may revoke their tokens but not introspect them
+    let confidential_auth_methods: Vec<&str> = TOKEN_ENDPOINT_AUTH_METHODS
+        .into_iter()
+        .filter(|method| *method != AUTH_METHOD_NONE)
+        .collect();
     Json(json!({
         "issuer": &issuer,
         "authorization_endpoint": format!("{}api/oidc/authorize", issuer),
         "token_endpoint": format!("{}api/oidc/token", issuer),
+        "pushed_authorization_request_endpoint": format!("{}api/oidc/par", issuer),
+        "require_pushed_authorization_requests": false,
+        "request_parameter_supported": true,
+        "request_uri_parameter_supported": true,
+        "require_request_uri_registration": false,
+        "request_object_signing_alg_values_supported": CLIENT_ASSERTION_SIGNING_ALGORITHMS,
+        "device_authorization_endpoint": format!("{}api/oidc/device_authorization", issuer),
+        "token_endpoint_auth_methods_supported": TOKEN_ENDPOINT_AUTH_METHODS,
+        "token_endpoint_auth_signing_alg_values_supported": CLIENT_ASSERTION_SIGNING_ALGORITHMS,
         "userinfo_endpoint": format!("{}api/oidc/userinfo", issuer),
+        "introspection_endpoint": format!("{}api/oidc/introspect", issuer),
+        "introspection_endpoint_auth_methods_supported": confidential_auth_methods,
+        "revocation_endpoint": format!("{}api/oidc/revoke", issuer),
+        "revocation_endpoint_auth_methods_supported": TOKEN_ENDPOINT_AUTH_METHODS,
+        "end_session_endpoint": format!("{}api/oidc/end_session", issuer),
+        "backchannel_logout_supported": true,
+        "backchannel_logout_session_supported": true,
+        "frontchannel_logout_supported": true,
+        "frontchannel_logout_session_supported": true,
         "jwks_uri": format!("{}.well-known/jwks.json", issuer),
-        "response_types_supported": ["code"],
+        "registration_endpoint": format!("{}api/oidc/register", issuer),
+        "response_types_supported": RESPONSE_TYPES,
+        "response_modes_supported": RESPONSE_MODES,
+        "prompt_values_supported": PROMPT_VALUES,
         "subject_types_supported": ["public"],
-        "id_token_signing_alg_values_supported": ["RS256"],
-        "scopes_supported": ["openid", "profile", "email"],
+        "id_token_signing_alg_values_supported": *SIGNING_ALGORITHMS,
+        "userinfo_signing_alg_values_supported": *SIGNING_ALGORITHMS,
+        "claims_supported": [
+            "sub",
+            "name",
+            "preferred_username",
+            "nickname",
+            "picture",
+            "updated_at",
+            "email",
+            "email_verified",
+        ],
+        "scopes_supported": ["openid", "profile", "email", "offline_access"],
+        "grant_types_supported": GRANT_TYPES,
+        "code_challenge_methods_supported": CODE_CHALLENGE_METHODS,
+        "dpop_signing_alg_values_supported": DPOP_SIGNING_ALGORITHMS,
     }))
 }
@@ -0,0 +1,39 @@
+use axum::extract::{Extension, Path};
+use sea_orm::DatabaseConnection;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::{AppError, ServiceError},
+    extractor::user_id_from_session::UserIdFromSession,
+    model::application_access_grant::ApplicationAccessGrantModel,
+    response::OkResponse,
+};
+
+#[derive(Serialize)]
+pub struct SuccessResponse {}
+
+#[derive(Deserialize)]
+pub struct DeleteGrantUrlParams {
+    pub grant_id: i32,
+}
+
+pub async fn handler(
+    Extension(conn): Extension<DatabaseConnection>,
+    Path(url_params): Path<DeleteGrantUrlParams>,
+    user_id_from_session: UserIdFromSession,
+) -> Result<OkResponse<SuccessResponse>, AppError> {
+    let grant_model = ApplicationAccessGrantModel::new(&conn);
+
+    let grant = grant_model
+        .find_one_grant_by_id(&url_params.grant_id)
+        .await?
+        .ok_or(ServiceError::NotFound)?;
+
+    if grant.user_id != user_id_from_session.user_id {
+        return Err(ServiceError::PermissionDenied.into());
+    }
+
+    grant_model.revoke_grant(grant).await?;
+
+    Ok(OkResponse::new(SuccessResponse {}))
+}
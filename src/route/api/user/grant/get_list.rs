@@ -0,0 +1,53 @@
+use axum::extract::Extension;
+use sea_orm::DatabaseConnection;
+use serde::Serialize;
+
+use crate::{
+    error::AppError,
+    extractor::user_id_from_session::UserIdFromSession,
+    model::application_access_grant::ApplicationAccessGrantModel,
+    response::OkResponse,
+    route::api::oidc::scope::{scopes_from_json, Scopes},
+};
+
+#[derive(Serialize)]
+struct ResponseGrant {
+    id: i32,
+    application_id: String,
+    application_name: Option<String>,
+    scopes: Scopes,
+    created_at: String,
+    updated_at: String,
+}
+
+#[derive(Serialize)]
+pub struct SuccessResponse {
+    grants: Vec<ResponseGrant>,
+}
+
+pub async fn handler(
+    Extension(conn): Extension<DatabaseConnection>,
+    user_id_from_session: UserIdFromSession,
+) -> Result<OkResponse<SuccessResponse>, AppError> {
+    let grant_model = ApplicationAccessGrantModel::new(&conn);
+
+    let grants = grant_model
+        .find_grants_by_user_id(&user_id_from_session.user_id)
+        .await?;
+
+    let res = SuccessResponse {
+        grants: grants
+            .into_iter()
+            .map(|(grant, application)| ResponseGrant {
+                id: grant.id,
+                application_id: grant.app_id,
+                application_name: application.map(|app| app.name),
+                scopes: scopes_from_json(&grant.scopes),
+                created_at: grant.created_at.to_string(),
+                updated_at: grant.updated_at.to_string(),
+            })
+            .collect(),
+    };
+
+    Ok(OkResponse::new(res))
+}
@@ -0,0 +1,65 @@
+use axum::{extract::Extension, Json};
+use sea_orm::DatabaseConnection;
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+use crate::{
+    error::AppError,
+    extractor::user_id_from_session::UserIdFromSession,
+    model::initial_access_token::{CreateInitialAccessTokenParams, InitialAccessTokenModel},
+    response::OkResponse,
+};
+
+const DEFAULT_EXPIRES_IN_DAYS: i64 = 30;
+
+#[derive(Serialize)]
+struct ResponseInitialAccessToken {
+    id: i32,
+    token: String,
+    expires_at: String,
+    created_at: String,
+}
+
+#[derive(Serialize)]
+pub struct SuccessResponse {
+    initial_access_token: ResponseInitialAccessToken,
+}
+
+#[derive(Deserialize, Validate)]
+pub struct CreateInitialAccessTokenPostParams {
+    #[validate(range(min = 1, max = 365))]
+    expires_in_days: Option<i64>,
+}
+
+// Initial access tokens let automation register clients through
+// `/api/oidc/register`. Clients registered with one belong to its creator.
+pub async fn handler(
+    Extension(conn): Extension<DatabaseConnection>,
+    user_id_from_session: UserIdFromSession,
+    Json(create_params): Json<CreateInitialAccessTokenPostParams>,
+) -> Result<OkResponse<SuccessResponse>, AppError> {
+    create_params.validate()?;
+
+    let expires_in_days = create_params
+        .expires_in_days
+        .unwrap_or(DEFAULT_EXPIRES_IN_DAYS);
+
+    let new_token = InitialAccessTokenModel::new(&conn)
+        .insert_token(CreateInitialAccessTokenParams {
+            token: uuid::Uuid::new_v4().to_string(),
+            user_id: user_id_from_session.user_id,
+            expires_at: chrono::Utc::now().naive_utc() + chrono::Duration::days(expires_in_days),
+        })
+        .await?;
+
+    let res = SuccessResponse {
+        initial_access_token: ResponseInitialAccessToken {
+            id: new_token.id,
+            token: new_token.token,
+            expires_at: new_token.expires_at.to_string(),
+            created_at: new_token.created_at.to_string(),
+        },
+    };
+
+    Ok(OkResponse::new(res))
+}
@@ -0,0 +1,39 @@
+use axum::extract::{Extension, Path};
+use sea_orm::DatabaseConnection;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::{AppError, ServiceError},
+    extractor::user_id_from_session::UserIdFromSession,
+    model::initial_access_token::InitialAccessTokenModel,
+    response::OkResponse,
+};
+
+#[derive(Serialize)]
+pub struct SuccessResponse {}
+
+#[derive(Deserialize)]
+pub struct DeleteInitialAccessTokenUrlParams {
+    pub token_id: i32,
+}
+
+pub async fn handler(
+    Extension(conn): Extension<DatabaseConnection>,
+    Path(url_params): Path<DeleteInitialAccessTokenUrlParams>,
+    user_id_from_session: UserIdFromSession,
+) -> Result<OkResponse<SuccessResponse>, AppError> {
+    let token_model = InitialAccessTokenModel::new(&conn);
+
+    let token = token_model
+        .find_one_token_by_id(&url_params.token_id)
+        .await?
+        .ok_or(ServiceError::NotFound)?;
+
+    if token.user_id != user_id_from_session.user_id {
+        return Err(ServiceError::PermissionDenied.into());
+    }
+
+    token_model.delete_token(&token.id).await?;
+
+    Ok(OkResponse::new(SuccessResponse {}))
+}
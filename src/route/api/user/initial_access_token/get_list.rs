@@ -0,0 +1,44 @@
+use axum::extract::Extension;
+use sea_orm::DatabaseConnection;
+use serde::Serialize;
+
+use crate::{
+    error::AppError, extractor::user_id_from_session::UserIdFromSession,
+    model::initial_access_token::InitialAccessTokenModel, response::OkResponse, util::mask_secret,
+};
+
+#[derive(Serialize)]
+struct ResponseInitialAccessToken {
+    id: i32,
+    token: String,
+    expires_at: String,
+    created_at: String,
+}
+
+#[derive(Serialize)]
+pub struct SuccessResponse {
+    initial_access_tokens: Vec<ResponseInitialAccessToken>,
+}
+
+pub async fn handler(
+    Extension(conn): Extension<DatabaseConnection>,
+    user_id_from_session: UserIdFromSession,
+) -> Result<OkResponse<SuccessResponse>, AppError> {
+    let tokens = InitialAccessTokenModel::new(&conn)
+        .find_tokens_by_user_id(&user_id_from_session.user_id)
+        .await?;
+
+    let res = SuccessResponse {
+        initial_access_tokens: tokens
+            .into_iter()
+            .map(|token| ResponseInitialAccessToken {
+                id: token.id,
+                token: mask_secret(&token.token),
+                expires_at: token.expires_at.to_string(),
+                created_at: token.created_at.to_string(),
+            })
+            .collect(),
+    };
+
+    Ok(OkResponse::new(res))
+}
@@ -1,2 +1,4 @@
+pub mod grant;
+pub mod initial_access_token;
 pub mod patch;
 pub mod user;
@@ -4,7 +4,7 @@ use async_redis_session::RedisSessionStore;
 use aws_sdk_s3::Client;
 use axum::extract::Extension;
 use axum::{
-    routing::{get, patch, post},
+    routing::{delete, get, patch, post, put},
     Router,
 };
 use http::header::{ACCEPT, AUTHORIZATION, CONTENT_TYPE};
@@ -19,11 +19,13 @@ use tower_http::{
 mod api;
 mod hello_world;
 
-use crate::route::api::oidc::well_known::OidcKeys;
+pub use crate::route::api::oidc::backchannel_logout::revoke_user_sessions;
+pub use crate::route::api::oidc::keys::OidcKeys;
 
 pub async fn get_app(
     conn: DatabaseConnection,
     session_store: RedisSessionStore,
+    redis_client: redis::Client,
     s3_client: Client,
 ) -> Router {
     let is_prod = ENVS.prod;
@@ -32,16 +34,84 @@ pub async fn get_app(
         Migrator::up(&conn, None).await.expect("Migrator up failed");
     }
 
-    let oidc_keys = OidcKeys::new();
+    let oidc_keys = OidcKeys::load(&conn)
+        .await
+        .expect("Failed to load OIDC signing keys");
+    oidc_keys.spawn_maintenance(conn.clone());
 
     let front_end_url = PARSED_FRONTEND_URL.to_string();
     let front_end_url = front_end_url.trim_end_matches("/");
 
+    routes()
+        .layer(Extension(conn))
+        .layer(Extension(session_store))
+        .layer(Extension(redis_client))
+        .layer(Extension(s3_client))
+        .layer(Extension(oidc_keys))
+        .layer(TraceLayer::new_for_http())
+        .layer(
+            CorsLayer::new()
+                .allow_origin(AllowOrigin::exact(front_end_url.parse().unwrap()))
+                .allow_methods(vec![
+                    Method::GET,
+                    Method::POST,
+                    Method::PATCH,
+                    Method::PUT,
+                    Method::DELETE,
+                ])
+                .allow_headers(vec![AUTHORIZATION, ACCEPT, CONTENT_TYPE])
+                .allow_credentials(true),
+        )
+}
+
+fn routes() -> Router {
     Router::new()
         .route("/", get(hello_world::handler))
         .route("/api/oidc/authorize", get(api::oidc::authorize::handler))
         .route("/api/oidc/token", post(api::oidc::token::handler))
+        .route("/api/oidc/par", post(api::oidc::par::handler))
+        .route(
+            "/api/oidc/device_authorization",
+            post(api::oidc::device::authorization_handler),
+        )
+        .route("/api/oidc/device", get(api::oidc::device::get_handler))
+        .route("/api/oidc/device", post(api::oidc::device::post_handler))
         .route("/api/oidc/userinfo", get(api::oidc::userinfo::handler))
+        .route("/api/oidc/introspect", post(api::oidc::introspect::handler))
+        .route("/api/oidc/revoke", post(api::oidc::revoke::handler))
+        .route("/api/oidc/denylist", post(api::oidc::denylist::handler))
+        .route(
+            "/api/oidc/end_session",
+            get(api::oidc::end_session::get_handler),
+        )
+        .route(
+            "/api/oidc/end_session",
+            post(api::oidc::end_session::post_handler),
+        )
+        .route(
+            "/api/oidc/register",
+            post(api::oidc::register::post_handler),
+        )
+        .route(
+            "/api/oidc/register/:client_id",
+            get(api::oidc::register::get_handler),
+        )
+        .route(
+            "/api/oidc/register/:client_id",
+            put(api::oidc::register::put_handler),
+        )
+        .route(
+            "/api/oidc/register/:client_id",
+            delete(api::oidc::register::delete_handler),
+        )
+        .route(
+            "/api/oidc/consent/:consent_id",
+            get(api::oidc::consent::get_handler),
+        )
+        .route(
+            "/api/oidc/consent/:consent_id",
+            post(api::oidc::consent::post_handler),
+        )
         .route(
             "/.well-known/jwks.json",
             get(api::oidc::well_known::jwks_handler),
@@ -52,6 +122,23 @@ pub async fn get_app(
         )
         .route("/api/user", get(api::user::user::handler))
         .route("/api/user", patch(api::user::patch::handler))
+        .route("/api/user/grants", get(api::user::grant::get_list::handler))
+        .route(
+            "/api/user/grants/:grant_id",
+            delete(api::user::grant::delete::handler),
+        )
+        .route(
+            "/api/user/initial-access-tokens",
+            get(api::user::initial_access_token::get_list::handler),
+        )
+        .route(
+            "/api/user/initial-access-tokens",
+            post(api::user::initial_access_token::create::handler),
+        )
+        .route(
+            "/api/user/initial-access-tokens/:token_id",
+            delete(api::user::initial_access_token::delete::handler),
+        )
         .route("/api/auth/register", post(api::auth::register::handler))
         .route("/api/auth/login", post(api::auth::login::handler))
         .route("/api/auth/logout", post(api::auth::logout::handler))
@@ -64,6 +151,10 @@ pub async fn get_app(
             "/api/application/:application_id",
             get(api::application::single::handler),
         )
+        .route(
+            "/api/application/:application_id",
+            patch(api::application::patch::handler),
+        )
         .route(
             "/api/application/:application_id/secrets",
             get(api::application::secret::get_list::handler),
@@ -72,16 +163,44 @@ pub async fn get_app(
             "/api/application/:application_id/secrets",
             post(api::application::secret::create::handler),
         )
-        .layer(Extension(conn))
-        .layer(Extension(session_store))
-        .layer(Extension(s3_client))
-        .layer(Extension(oidc_keys))
-        .layer(TraceLayer::new_for_http())
-        .layer(
-            CorsLayer::new()
-                .allow_origin(AllowOrigin::exact(front_end_url.parse().unwrap()))
-                .allow_methods(vec![Method::GET, Method::POST, Method::PATCH])
-                .allow_headers(vec![AUTHORIZATION, ACCEPT, CONTENT_TYPE])
-                .allow_credentials(true),
+        .route(
+            "/api/application/:application_id/token-exchange-policies",
+            get(api::application::token_exchange_policy::get_list::handler),
+        )
+        .route(
+            "/api/application/:application_id/token-exchange-policies",
+            post(api::application::token_exchange_policy::create::handler),
         )
+        .route(
+            "/api/application/:application_id/token-exchange-policies/:policy_id",
+            delete(api::application::token_exchange_policy::delete::handler),
+        )
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{
+        body::Body,
+        http::{Request, StatusCode},
+    };
+    use tower::ServiceExt;
+
+    use super::routes;
+
+    async fn is_routed(path: &str) -> bool {
+        let request = Request::post(path).body(Body::empty()).unwrap();
+        let response = routes().oneshot(request).await.unwrap();
+
+        response.status() != StatusCode::NOT_FOUND
+    }
+
+    #[tokio::test]
+    async fn revocation_endpoint_is_routed() {
+        assert!(is_routed("/api/oidc/revoke").await);
+    }
+
+    #[tokio::test]
+    async fn denylist_endpoint_is_routed() {
+        assert!(is_routed("/api/oidc/denylist").await);
+    }
 }
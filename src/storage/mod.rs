@@ -1,3 +1,4 @@
 pub mod mysql;
+pub mod redis;
 pub mod s3;
 pub mod session;
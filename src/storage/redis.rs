@@ -0,0 +1,31 @@
+use redis::{AsyncCommands, Client, RedisResult};
+
+use crate::constants::ENVS;
+
+pub fn get_redis_client() -> Client {
+    let db_url = &ENVS.redis_url;
+
+    Client::open(db_url.clone()).expect("Create redis client error")
+}
+
+// Remember `key` for `ttl` seconds. Returns false when it was already taken,
+// which is how one-time values such as assertion `jti`s are enforced.
+pub async fn set_once(client: &Client, key: &str, ttl: usize) -> RedisResult<bool> {
+    let mut conn = client.get_async_connection().await?;
+    let result: Option<String> = redis::cmd("SET")
+        .arg(key)
+        .arg(1)
+        .arg("NX")
+        .arg("EX")
+        .arg(ttl.max(1))
+        .query_async(&mut conn)
+        .await?;
+
+    Ok(result.is_some())
+}
+
+pub async fn delete_key(client: &Client, key: &str) -> RedisResult<()> {
+    let mut conn = client.get_async_connection().await?;
+
+    conn.del(key).await
+}
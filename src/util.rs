@@ -3,7 +3,13 @@ use async_session::SessionStore;
 use base64::prelude::*;
 use openssl::{
     error::ErrorStack,
+    hash::MessageDigest,
+    memcmp,
+    pkey::PKey,
+    rand::rand_bytes,
     rsa::{Padding, Rsa},
+    sign::Signer,
+    symm::{decrypt_aead, encrypt_aead, Cipher},
 };
 use pbkdf2::{
     password_hash::{Error, PasswordHasher, SaltString},
@@ -13,7 +19,7 @@ use rand_core::OsRng;
 use validator::ValidationError;
 
 use crate::{
-    constants::RSA_PRIVATE_KEY_REDIS_KEY,
+    constants::{RSA_PRIVATE_KEY_REDIS_KEY, SECRET_KEY},
     error::{AppError, ServiceError},
 };
 
@@ -89,3 +95,156 @@ pub fn mask_secret(secret: &String) -> String {
     let prefix = &secret[0..8];
     format!("{}{}", prefix, "*".repeat(27))
 }
+
+pub fn hmac_sha256(content: &[u8]) -> Result<Vec<u8>, ErrorStack> {
+    let key = PKey::hmac(SECRET_KEY.as_bytes())?;
+    let mut signer = Signer::new(MessageDigest::sha256(), &key)?;
+    signer.update(content)?;
+    signer.sign_to_vec()
+}
+
+// The front-end passes the signed value back to the login API untouched, so a
+// login can only ever bounce the browser to a path this service handed out.
+pub fn sign_return_to(path: &str) -> Result<String, ErrorStack> {
+    let signature = hmac_sha256(path.as_bytes())?;
+    Ok(format!(
+        "{}.{}",
+        BASE64_URL_SAFE_NO_PAD.encode(path),
+        BASE64_URL_SAFE_NO_PAD.encode(signature)
+    ))
+}
+
+pub fn verify_return_to(return_to: &str) -> Option<String> {
+    let (path, signature) = return_to.split_once('.')?;
+    let path = String::from_utf8(BASE64_URL_SAFE_NO_PAD.decode(path).ok()?).ok()?;
+    let signature = BASE64_URL_SAFE_NO_PAD.decode(signature).ok()?;
+    let expected = hmac_sha256(path.as_bytes()).ok()?;
+
+    if signature.len() != expected.len() || !memcmp::eq(&signature, &expected) {
+        return None;
+    }
+
+    if !path.starts_with('/') || path.starts_with("//") {
+        return None;
+    }
+
+    Some(path)
+}
+
+const DPOP_NONCE_EXPIRES_IN: i64 = 5 * 60;
+
+fn dpop_nonce_signature(issued_at: i64) -> Result<Vec<u8>, ErrorStack> {
+    hmac_sha256(format!("dpop_nonce:{}", issued_at).as_bytes())
+}
+
+// DPoP nonces are signed timestamps, so any instance can check them without
+// keeping state. Replay within their lifetime is stopped by the proof `jti`.
+pub fn sign_dpop_nonce() -> Result<String, ErrorStack> {
+    let issued_at = chrono::Utc::now().timestamp();
+    Ok(format!(
+        "{}.{}",
+        issued_at,
+        BASE64_URL_SAFE_NO_PAD.encode(dpop_nonce_signature(issued_at)?)
+    ))
+}
+
+pub fn verify_dpop_nonce(nonce: &str) -> bool {
+    let Some((issued_at, signature)) = nonce.split_once('.') else {
+        return false;
+    };
+    let (Ok(issued_at), Ok(signature)) = (
+        issued_at.parse::<i64>(),
+        BASE64_URL_SAFE_NO_PAD.decode(signature),
+    ) else {
+        return false;
+    };
+    let Ok(expected) = dpop_nonce_signature(issued_at) else {
+        return false;
+    };
+
+    signature.len() == expected.len()
+        && memcmp::eq(&signature, &expected)
+        && (0..=DPOP_NONCE_EXPIRES_IN).contains(&(chrono::Utc::now().timestamp() - issued_at))
+}
+
+const AEAD_NONCE_LENGTH: usize = 12;
+const AEAD_TAG_LENGTH: usize = 16;
+
+// Secrets at rest are sealed with AES-256-GCM under a key derived from
+// SECRET_KEY. `aad` binds the ciphertext to the row it belongs to.
+pub fn encrypt_secret(plaintext: &[u8], aad: &[u8]) -> Result<String, ErrorStack> {
+    let key = hmac_sha256(b"secret-encryption-key")?;
+    let mut nonce = [0u8; AEAD_NONCE_LENGTH];
+    rand_bytes(&mut nonce)?;
+
+    let mut tag = [0u8; AEAD_TAG_LENGTH];
+    let ciphertext = encrypt_aead(
+        Cipher::aes_256_gcm(),
+        &key,
+        Some(&nonce),
+        aad,
+        plaintext,
+        &mut tag,
+    )?;
+
+    Ok(BASE64_STANDARD.encode([&nonce[..], &tag[..], &ciphertext[..]].concat()))
+}
+
+pub fn decrypt_secret(sealed: &str, aad: &[u8]) -> Result<Option<Vec<u8>>, ErrorStack> {
+    let sealed = match BASE64_STANDARD.decode(sealed) {
+        Ok(sealed) if sealed.len() >= AEAD_NONCE_LENGTH + AEAD_TAG_LENGTH => sealed,
+        _ => return Ok(None),
+    };
+    let (nonce, rest) = sealed.split_at(AEAD_NONCE_LENGTH);
+    let (tag, ciphertext) = rest.split_at(AEAD_TAG_LENGTH);
+    let key = hmac_sha256(b"secret-encryption-key")?;
+
+    decrypt_aead(
+        Cipher::aes_256_gcm(),
+        &key,
+        Some(nonce),
+        aad,
+        ciphertext,
+        tag,
+    )
+    .map(Some)
+}
+
+#[cfg(test)]
+mod tests {
+    use base64::prelude::*;
+
+    use super::{decrypt_secret, encrypt_secret, sign_return_to, verify_return_to};
+
+    #[test]
+    fn return_to_round_trips_and_rejects_tampering() {
+        unsafe {
+            std::env::set_var("SECRET_KEY", "test-secret-key");
+        }
+
+        let path = "/api/oidc/authorize?client_id=app&scope=openid";
+        let signed = sign_return_to(path).unwrap();
+
+        assert_eq!(verify_return_to(&signed).as_deref(), Some(path));
+
+        let (_, signature) = signed.split_once('.').unwrap();
+        let forged = format!("{}.{}", BASE64_URL_SAFE_NO_PAD.encode("/evil"), signature);
+        assert_eq!(verify_return_to(&forged), None);
+    }
+
+    #[test]
+    fn sealed_secret_round_trips_only_with_same_aad() {
+        unsafe {
+            std::env::set_var("SECRET_KEY", "test-secret-key");
+        }
+
+        let sealed = encrypt_secret(b"private key", b"kid-1").unwrap();
+
+        assert_eq!(
+            decrypt_secret(&sealed, b"kid-1").unwrap().as_deref(),
+            Some(&b"private key"[..])
+        );
+        assert!(decrypt_secret(&sealed, b"kid-2").is_err());
+        assert_eq!(decrypt_secret("not sealed", b"kid-1").unwrap(), None);
+    }
+}